@@ -1,21 +1,98 @@
+use std::{path::PathBuf, time::Duration};
+
 use clap::Parser;
 
+use stamon::feeds::backlight::{self, control};
+
+#[derive(Debug, clap::Subcommand)]
+enum Cmd {
+    /// Set the brightness percentage.
+    Set { pct: f32 },
+
+    /// Increase the brightness by this many percent.
+    Inc { pct: f32 },
+
+    /// Decrease the brightness by this many percent.
+    Dec { pct: f32 },
+}
+
 #[derive(Parser, Debug)]
 struct Cli {
     /// Log level.
     #[clap(short, long, default_value_t = tracing::Level::INFO)]
     log_level: tracing::Level,
 
-    #[clap(long = "device", default_value = "intel_backlight")]
-    device: String,
+    /// Backlight (or leds) device name, like "intel_backlight". Can be
+    /// repeated. Auto-detected if not given.
+    #[clap(long = "device")]
+    devices: Vec<String>,
+
+    /// Also display keyboard backlights.
+    #[clap(short, long, default_value_t = false)]
+    keyboard: bool,
 
     #[clap(long = "prefix", default_value = "☀ ")]
     prefix: String,
+
+    /// Accept "set|inc|dec <pct>" commands, one per line, from this FIFO,
+    /// while running the feed.
+    #[clap(long)]
+    control: Option<PathBuf>,
+
+    /// Steps are on the perceived scale, where the raw brightness is the
+    /// perceived one raised to this. 1 is linear, 4 looks even.
+    #[clap(short, long, default_value_t = 1.0)]
+    exponent: f32,
+
+    /// Never change the brightness to below this percentage.
+    #[clap(long, default_value_t = 1.0)]
+    min: f32,
+
+    /// Poll for changes from the start, rather than only once any are seen
+    /// to go unnoticed. Devices coming and going (external monitors, USB
+    /// keyboards) are likewise only noticed by polling, every 10 seconds
+    /// until then.
+    #[clap(long, default_value_t = false)]
+    poll: bool,
+
+    /// Polling interval seconds.
+    #[clap(short = 'i', long = "interval", default_value_t = 1.0)]
+    interval: f64,
+
+    /// Change the brightness (of the first device), rather than display it.
+    #[clap(subcommand)]
+    cmd: Option<Cmd>,
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     stamon::logger::init(cli.log_level)?;
     tracing::info!("cli: {:#?}", &cli);
-    stamon::feeds::backlight::run(&cli.device, &cli.prefix)
+    let selection = backlight::Selection {
+        devices: cli.devices,
+        keyboard: cli.keyboard,
+    };
+    let scale = control::Scale {
+        exponent: cli.exponent,
+        min: cli.min,
+    };
+    let change = match cli.cmd {
+        None => {
+            if let Some(fifo) = &cli.control {
+                control::listen(fifo, selection.clone(), scale)?;
+            }
+            let polling = backlight::Polling {
+                always: cli.poll,
+                interval: Duration::from_secs_f64(cli.interval),
+                ..backlight::Polling::default()
+            };
+            return backlight::run(selection, polling, &cli.prefix);
+        }
+        Some(Cmd::Set { pct }) => control::Change::Set(pct),
+        Some(Cmd::Inc { pct }) => control::Change::Inc(pct),
+        Some(Cmd::Dec { pct }) => control::Change::Dec(pct),
+    };
+    let pct = control::change(&selection, change, &scale)?;
+    println!("{}", pct);
+    Ok(())
 }
@@ -0,0 +1,31 @@
+use clap::Parser;
+
+use stamon::feeds::cpu;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    #[clap(long = "interval", short = 'i', default_value = "2")]
+    interval: u64,
+
+    #[clap(long = "prefix", default_value = "c ")]
+    prefix: String,
+
+    /// What to display. Can be repeated.
+    #[clap(long = "field", value_enum, default_values_t = [cpu::Field::Total])]
+    fields: Vec<cpu::Field>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    cpu::run(
+        &cli.prefix,
+        std::time::Duration::from_secs(cli.interval),
+        &cli.fields,
+    )
+}
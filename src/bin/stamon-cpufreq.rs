@@ -0,0 +1,40 @@
+use clap::Parser;
+
+use stamon::feeds::cpufreq;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    #[clap(long = "interval", short = 'i', default_value = "2")]
+    interval: u64,
+
+    #[clap(long = "prefix", default_value = "f ")]
+    prefix: String,
+
+    /// What to display. Can be repeated.
+    #[clap(
+        long = "field",
+        value_enum,
+        default_values_t = [cpufreq::Field::Avg, cpufreq::Field::Throttle]
+    )]
+    fields: Vec<cpufreq::Field>,
+
+    /// Seconds to keep the throttle marker up for, after the throttling.
+    #[clap(long, default_value_t = 10)]
+    throttle_hold: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    cpufreq::run(
+        &cli.prefix,
+        std::time::Duration::from_secs(cli.interval),
+        &cli.fields,
+        std::time::Duration::from_secs(cli.throttle_hold),
+    )
+}
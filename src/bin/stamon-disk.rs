@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::Parser;
 
+use stamon::feeds::disk;
+
 #[derive(Parser, Debug)]
 struct Cli {
     /// Log level.
@@ -16,18 +20,67 @@ struct Cli {
     #[clap(long = "postfix", default_value = "")]
     postfix: String,
 
-    #[clap(default_value = "/")]
-    path: String,
+    /// Find the real filesystems from /proc/self/mounts, rather than take
+    /// the given paths.
+    #[clap(short, long, default_value_t = false)]
+    auto: bool,
+
+    /// With --auto, only filesystems of this type. Can be repeated.
+    #[clap(long = "fstype")]
+    fstypes: Vec<String>,
+
+    /// With --auto, only mountpoints matching this glob. Can be repeated.
+    #[clap(long = "mountpoint")]
+    mountpoints: Vec<String>,
+
+    /// What to display of each. Can be repeated.
+    #[clap(long = "field", value_enum, default_values_t = [disk::Field::Pct])]
+    fields: Vec<disk::Field>,
+
+    /// Usage percentage which, when reached, triggers an alert:
+    /// <pct>[:<level>][@<mount>], where mount is a label or path, and
+    /// level is lo, mid or hi. Can be repeated.
+    #[clap(long = "alert", short = 'A')]
+    alerts: Vec<disk::alerts::AlertTrigger>,
+
+    /// Percentage points usage must drop below a threshold by, before it
+    /// can alert again.
+    #[clap(long = "alert-hysteresis", default_value_t = 5)]
+    alert_hysteresis: u64,
+
+    /// Alert when, at the rate of growth over about as many minutes as
+    /// given (but at least one), a disk would be full within them.
+    #[clap(long = "alert-fill")]
+    alert_fill: Option<u64>,
+
+    /// Paths, optionally labeled: [<label>=]<path>. Defaults to "/".
+    paths: Vec<disk::Mount>,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     stamon::logger::init(cli.log_level)?;
     tracing::info!("cli: {:#?}", &cli);
-    stamon::feeds::disk::run(
+    let selection = if cli.auto {
+        disk::Selection::Mounted(disk::mounts::Filter {
+            fstypes: cli.fstypes,
+            mountpoints: cli.mountpoints,
+        })
+    } else if cli.paths.is_empty() {
+        disk::Selection::Paths(vec!["/".parse()?])
+    } else {
+        disk::Selection::Paths(cli.paths)
+    };
+    disk::run(
         &cli.prefix,
         &cli.postfix,
-        std::time::Duration::from_secs(cli.interval),
-        &cli.path,
+        Duration::from_secs(cli.interval),
+        &selection,
+        &cli.fields,
+        disk::alerts::Settings {
+            triggers: cli.alerts,
+            hysteresis: cli.alert_hysteresis,
+            fill_within: cli.alert_fill.map(|m| Duration::from_secs(m * 60)),
+        },
     )
 }
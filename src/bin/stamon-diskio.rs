@@ -0,0 +1,46 @@
+use clap::Parser;
+
+use stamon::feeds::diskio;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    #[clap(long = "interval", short = 'i', default_value = "5")]
+    interval: u64,
+
+    #[clap(long = "prefix", default_value = "io ")]
+    prefix: String,
+
+    /// Block device name, as in /proc/diskstats, like "nvme0n1" or "sda1".
+    /// Can be repeated. Defaults to all physical disks.
+    #[clap(long = "device")]
+    devices: Vec<String>,
+
+    /// Display each device separately, rather than their total.
+    #[clap(short, long, default_value_t = false)]
+    per_device: bool,
+
+    /// Also display the percentage of time busy (of the busiest device,
+    /// when totaled).
+    #[clap(short, long, default_value_t = false)]
+    util: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    let selection = diskio::Selection {
+        devices: cli.devices,
+    };
+    diskio::run(
+        &cli.prefix,
+        std::time::Duration::from_secs(cli.interval),
+        &selection,
+        cli.per_device,
+        cli.util,
+    )
+}
@@ -0,0 +1,38 @@
+use clap::Parser;
+
+use stamon::feeds::leds;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    /// Polling interval seconds, since most LEDs do not notify of changes.
+    #[clap(long = "interval", short = 'i', default_value = "1.0")]
+    interval: f64,
+
+    #[clap(long = "prefix", default_value = "")]
+    prefix: String,
+
+    /// LED function to show, if present, and the symbol to show it with:
+    /// <function>=<symbol>, like: --led capslock=A --led micmute=M
+    /// Defaults to keyboard backlight, lock keys and mute LEDs.
+    #[clap(long = "led")]
+    leds: Vec<leds::Indicator>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    if cli.leds.is_empty() {
+        cli.leds =
+            leds::DEFAULT_INDICATORS.map(leds::Indicator::from).to_vec();
+    }
+    leds::run(
+        &cli.leds,
+        std::time::Duration::from_secs_f64(cli.interval),
+        &cli.prefix,
+    )
+}
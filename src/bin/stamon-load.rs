@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use clap::Parser;
+
+use stamon::feeds::load;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    #[clap(long = "interval", short = 'i', default_value = "5")]
+    interval: u64,
+
+    #[clap(long = "prefix", default_value = "l ")]
+    prefix: String,
+
+    /// What to display. Can be repeated.
+    #[clap(long = "field", value_enum, default_values_t = [load::Field::Load])]
+    fields: Vec<load::Field>,
+
+    /// Display loads divided by the number of CPUs.
+    #[clap(short, long, default_value_t = false)]
+    per_cpu: bool,
+
+    /// Alert when the 1-minute load per CPU stays above this, for as long
+    /// as --alert-for.
+    #[clap(long = "alert-load")]
+    alert_load: Option<f32>,
+
+    /// Seconds.
+    #[clap(long = "alert-for", default_value_t = 60)]
+    alert_for: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    let settings = load::Settings {
+        prefix: cli.prefix,
+        fields: cli.fields,
+        per_cpu: cli.per_cpu,
+        alert_load: cli.alert_load,
+        alert_for: Duration::from_secs(cli.alert_for),
+    };
+    load::run(&settings, Duration::from_secs(cli.interval))
+}
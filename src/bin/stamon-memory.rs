@@ -1,5 +1,7 @@
 use clap::Parser;
 
+use stamon::feeds::mem;
+
 #[derive(Debug, Parser)]
 struct Cli {
     /// Log level.
@@ -11,14 +13,39 @@ struct Cli {
 
     #[clap(long = "prefix", default_value = "m ")]
     prefix: String,
+
+    /// What to display. Can be repeated.
+    #[clap(long = "field", value_enum, default_values_t = [mem::Field::Pct])]
+    fields: Vec<mem::Field>,
+
+    /// Used percentage which, when reached, triggers an alert:
+    /// <pct>[:<level>], where level is lo, mid or hi. Can be repeated.
+    #[clap(long = "alert", short)]
+    alerts: Vec<stamon::alert::AlertTrigger>,
+
+    /// Memory pressure (PSI some avg10) percentage which, when reached,
+    /// triggers an alert: <pct>[:<level>]. Can be repeated.
+    #[clap(long = "alert-pressure")]
+    alert_pressure: Vec<stamon::alert::AlertTrigger>,
+
+    /// Alert when the OOM killer kills a process, naming it if the kernel
+    /// log is readable.
+    #[clap(long = "alert-oom", default_value_t = false)]
+    alert_oom: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     stamon::logger::init(cli.log_level)?;
     tracing::info!("cli: {:#?}", &cli);
-    stamon::feeds::mem::run(
+    mem::run(
         &cli.prefix,
         std::time::Duration::from_secs(cli.interval),
+        &cli.fields,
+        &mem::Settings {
+            alert_triggers: cli.alerts,
+            alert_pressure: cli.alert_pressure,
+            alert_oom: cli.alert_oom,
+        },
     )
 }
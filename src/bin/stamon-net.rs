@@ -6,6 +6,20 @@ use stamon::feeds::net;
 enum IFKind {
     Wifi,
     Eth,
+
+    /// Throughput. The interface can be "all", for all but loopback,
+    /// combined.
+    Traffic {
+        /// Write the rates and totals, as JSON, to this file on each
+        /// update.
+        #[clap(long)]
+        summary_file: Option<std::path::PathBuf>,
+
+        /// Include the cumulative totals in the summary, since boot or since
+        /// the feed started.
+        #[clap(long, value_enum)]
+        totals: Option<net::traffic::Totals>,
+    },
 }
 
 #[derive(Debug, clap::Parser)]
@@ -24,6 +38,11 @@ struct Cli {
 
     #[clap(long = "prefix", default_value = "net ")]
     prefix: String,
+
+    /// When to update link state and wifi link quality. Throughput is
+    /// always polled, as it is a rate.
+    #[clap(long, value_enum, default_value_t = net::netlink::Updates::Notify)]
+    updates: net::netlink::Updates,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -35,11 +54,28 @@ fn main() -> anyhow::Result<()> {
         interval,
         prefix,
         interface_kind,
+        updates,
         ..
     } = &cli;
     let interval = std::time::Duration::from_secs(*interval);
     match interface_kind {
-        IFKind::Wifi => net::wifi_link_qual::run(interval, interface, prefix),
-        IFKind::Eth => net::if_operstate::run(interval, interface, prefix),
+        IFKind::Wifi => {
+            net::wifi_link_qual::run(interval, interface, prefix, *updates)
+        }
+        IFKind::Eth => {
+            net::if_operstate::run(interval, interface, prefix, *updates)
+        }
+        IFKind::Traffic {
+            summary_file,
+            totals,
+        } => net::traffic::run(
+            interval,
+            &interface.parse()?,
+            &net::traffic::Settings {
+                prefix: prefix.clone(),
+                summary_file: summary_file.clone(),
+                totals: *totals,
+            },
+        ),
     }
 }
@@ -0,0 +1,63 @@
+use clap::Parser;
+
+use stamon::feeds::temp;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Log level.
+    #[clap(short, long, default_value_t = tracing::Level::INFO)]
+    log_level: tracing::Level,
+
+    #[clap(long = "interval", short = 'i', default_value = "5")]
+    interval: u64,
+
+    #[clap(long = "prefix", default_value = "t ")]
+    prefix: String,
+
+    /// Chip name, like "coretemp", or sensor, like "coretemp/Package id 0"
+    /// or "x86_pkg_temp" (a thermal zone). Can be repeated. Defaults to
+    /// all, of which the hottest is displayed.
+    #[clap(long = "sensor", short)]
+    sensors: Vec<String>,
+
+    /// Display only the hottest of the selected sensors.
+    #[clap(long, default_value_t = false)]
+    max: bool,
+
+    #[clap(long, short, value_enum, default_value_t = temp::Unit::Celsius)]
+    unit: temp::Unit,
+
+    /// Also display fan speeds.
+    #[clap(long, short, default_value_t = false)]
+    fans: bool,
+
+    /// Temperature, in the chosen unit, which, when reached by any of the
+    /// selected sensors, triggers an alert: <degrees>[:<level>], where
+    /// level is lo, mid or hi. Can be repeated.
+    #[clap(long = "alert", short)]
+    alerts: Vec<temp::alerts::AlertTrigger>,
+
+    /// Alert when a sensor reaches its own max or critical limit.
+    #[clap(long = "alert-limits", default_value_t = false)]
+    alert_limits: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    stamon::logger::init(cli.log_level)?;
+    tracing::info!("cli: {:#?}", &cli);
+    let settings = temp::Settings {
+        prefix: cli.prefix,
+        selection: temp::Selection {
+            sensors: cli.sensors,
+        },
+        unit: cli.unit,
+        max: cli.max,
+        fans: cli.fans,
+        alerts: temp::alerts::Settings {
+            triggers: cli.alerts,
+            sensor_limits: cli.alert_limits,
+        },
+    };
+    temp::run(&settings, std::time::Duration::from_secs(cli.interval))
+}
@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::Parser;
 
+use stamon::feeds::upower::{self, Backend};
+
 const DEFAULT_ALERTS: [u64; 14] =
     [100, 75, 50, 40, 30, 25, 20, 15, 10, 5, 4, 3, 2, 1];
 
@@ -10,11 +14,97 @@ struct Cli {
     #[clap(short, long, default_value_t = tracing::Level::INFO)]
     log_level: tracing::Level,
 
+    /// Where to get the battery and line-power updates from.
+    #[clap(long, value_enum, default_value_t = Backend::Text)]
+    backend: Backend,
+
+    /// Polling interval seconds, for when the backend cannot be notified of
+    /// changes.
+    #[clap(short = 'i', long = "interval", default_value = "5.0")]
+    interval: f64,
+
     #[clap(long = "prefix", default_value = "⚡ ")]
     prefix: String,
 
+    /// Battery percentage which, when dropped below, triggers an alert.
+    /// Optionally with an urgency level: <percentage>[:<lo|mid|hi>].
+    /// Can be repeated, like: -a 20:mid -a 5:hi
     #[clap(long = "alert", short)]
-    alerts: Vec<u64>,
+    alerts: Vec<stamon::alert::AlertTrigger>,
+
+    /// Alert when the estimated time left on battery drops below this many
+    /// minutes.
+    #[clap(long = "alert-time")]
+    alert_time: Option<u64>,
+
+    /// Alert, once, when battery health (percentage of design capacity
+    /// left) is below this.
+    #[clap(long = "alert-health")]
+    alert_health: Option<u64>,
+
+    /// Alert when the power is plugged-in or unplugged.
+    #[clap(long, default_value_t = false)]
+    alert_plug: bool,
+
+    /// Alert when a battery becomes fully charged.
+    #[clap(long, default_value_t = false)]
+    alert_full: bool,
+
+    /// Alert when plugged-in, but a battery keeps discharging for this many
+    /// seconds (weak or faulty charger). Fires on the first battery update
+    /// after that, rather than right at the end of it, since updates are
+    /// what it is checked on.
+    #[clap(long)]
+    alert_not_charging: Option<u64>,
+
+    /// Alert when a peripheral's (mouse, headset, etc) battery percentage
+    /// drops below this.
+    #[clap(long)]
+    alert_peripheral: Option<u64>,
+
+    /// Display the estimated time left until empty or full, as H:MM.
+    #[clap(short, long, default_value_t = false)]
+    time: bool,
+
+    /// Display the current power draw (or charge) in watts.
+    #[clap(short = 'w', long, default_value_t = false)]
+    power: bool,
+
+    /// Display the battery health: percentage of design capacity left.
+    #[clap(long, default_value_t = false)]
+    health: bool,
+
+    /// Display the percentage of each battery, after the combined one.
+    #[clap(short, long, default_value_t = false)]
+    batteries: bool,
+
+    /// Display the percentage of each peripheral (mouse, headset, etc).
+    #[clap(short, long, default_value_t = false)]
+    peripherals: bool,
+
+    /// Write the battery details, as JSON, to this file on each update.
+    #[clap(long)]
+    summary_file: Option<std::path::PathBuf>,
+
+    /// Command to run, once, when the battery percentage drops below the
+    /// critical threshold while discharging. For example:
+    /// "systemctl suspend" or "loginctl lock-session".
+    #[clap(long)]
+    critical_action: Option<String>,
+
+    /// Battery percentage which, when dropped below while discharging,
+    /// triggers the critical action.
+    #[clap(
+        long,
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u64).range(0..=100)
+    )]
+    critical_threshold: u64,
+
+    /// Seconds to wait for AC to be plugged-in, before running the critical
+    /// action.
+    #[clap(long, default_value_t = 60)]
+    critical_grace: u64,
 }
 
 impl Cli {
@@ -24,16 +114,11 @@ impl Cli {
         // TODO: Is there really no way to define a default_value_t for a Vec<T>?
         // "`Vec<u64>` cannot be formatted with the default formatter" when
         // "default_value_t = DEFAULT_ALERTS.to_vec()"
-        let alert_triggers = if cli.alerts.is_empty() {
-            &DEFAULT_ALERTS[..]
-        } else {
-            &cli.alerts[..]
-        };
-        // TODO Integrate this validation with clap derive somehow:
-        if let Some(n) = alert_triggers.iter().find(|n| **n > 100) {
-            panic!("Alert value out of percentage range: {:?}", n)
+        if cli.alerts.is_empty() {
+            cli.alerts = DEFAULT_ALERTS
+                .map(stamon::alert::AlertTrigger::from)
+                .to_vec();
         }
-        cli.alerts = alert_triggers.to_vec();
         cli
     }
 }
@@ -42,5 +127,33 @@ fn main() -> Result<()> {
     let cli = Cli::parse_and_validate();
     stamon::logger::init(cli.log_level)?;
     tracing::info!("cli: {:#?}", &cli);
-    stamon::feeds::upower::run(&cli.prefix, &cli.alerts[..])
+    let settings = upower::Settings {
+        prefix: cli.prefix,
+        alert_triggers: cli.alerts,
+        alert_time_left: cli.alert_time.map(|m| Duration::from_secs(m * 60)),
+        alert_health: cli.alert_health,
+        alert_plug: cli.alert_plug,
+        alert_full: cli.alert_full,
+        alert_not_charging: cli.alert_not_charging.map(Duration::from_secs),
+        show_time: cli.time,
+        show_power: cli.power,
+        show_health: cli.health,
+        show_batteries: cli.batteries,
+        show_peripherals: cli.peripherals,
+        alert_peripheral: cli.alert_peripheral,
+        summary_file: cli.summary_file,
+        critical: cli.critical_action.map(|command| {
+            upower::critical::Settings {
+                command,
+                threshold: cli.critical_threshold,
+                grace: Duration::from_secs(cli.critical_grace),
+                spawn: upower::critical::spawn,
+            }
+        }),
+    };
+    upower::run(
+        cli.backend,
+        Duration::from_secs_f64(cli.interval),
+        &settings,
+    )
 }
@@ -1,11 +1,28 @@
 use notify_rust::{Notification, Urgency};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Level {
     Lo,
     Mid,
     Hi,
 }
 
+impl std::str::FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "lo" | "low" => Ok(Self::Lo),
+            "mid" | "normal" => Ok(Self::Mid),
+            "hi" | "high" | "critical" => Ok(Self::Hi),
+            _ => Err(anyhow::anyhow!(
+                "invalid alert level: {:?}. expected: lo | mid | hi",
+                s
+            )),
+        }
+    }
+}
+
 pub struct Alert {
     notification: Notification,
 }
@@ -29,3 +46,259 @@ impl Alert {
         Ok(())
     }
 }
+
+/// Value which, when crossed, triggers an alert of the given urgency level,
+/// or of one derived from the threshold, by whoever uses it, if not given.
+/// Parsed from "<threshold>[:<lo|mid|hi>]", like "20" or "5:hi".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertTrigger<T = u64> {
+    pub threshold: T,
+    pub level: Option<Level>,
+}
+
+impl<T> AlertTrigger<T> {
+    fn parse(
+        s: &str,
+        threshold: impl FnOnce(&str) -> Option<T>,
+    ) -> anyhow::Result<Self> {
+        let (t, level) = match s.split_once(':') {
+            None => (s, None),
+            Some((t, level)) => (t, Some(level.parse()?)),
+        };
+        let threshold = threshold(t).ok_or_else(|| {
+            anyhow::anyhow!("invalid alert threshold: {:?}", s)
+        })?;
+        Ok(Self { threshold, level })
+    }
+}
+
+impl<T> From<T> for AlertTrigger<T> {
+    fn from(threshold: T) -> Self {
+        Self {
+            threshold,
+            level: None,
+        }
+    }
+}
+
+/// Percentage.
+impl std::str::FromStr for AlertTrigger<u64> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let trigger = Self::parse(s, |t| t.parse().ok())?;
+        if trigger.threshold > 100 {
+            return Err(anyhow::anyhow!(
+                "Alert value out of percentage range: {:?}",
+                trigger.threshold
+            ));
+        }
+        Ok(trigger)
+    }
+}
+
+/// Anything measured, like a temperature.
+impl std::str::FromStr for AlertTrigger<f32> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::parse(s, |t| t.parse().ok())
+    }
+}
+
+/// Which way a value goes, towards trouble.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    /// Triggered when at or above the threshold, like disk usage.
+    Rising,
+
+    /// Triggered when below the threshold, like battery charge.
+    Falling,
+}
+
+impl<T: PartialOrd> AlertTrigger<T> {
+    /// As given or else, by default, by how far towards trouble the
+    /// threshold is: Hi from hi on, Mid from mid on, Lo short of both.
+    pub fn level(&self, direction: Direction, mid: T, hi: T) -> Level {
+        let past = |limit: &T| match direction {
+            Direction::Rising => self.threshold >= *limit,
+            Direction::Falling => self.threshold <= *limit,
+        };
+        match self.level {
+            Some(level) => level,
+            None if past(&hi) => Level::Hi,
+            None if past(&mid) => Level::Mid,
+            None => Level::Lo,
+        }
+    }
+}
+
+/// Alerts once per crossing: each trigger fires once, when its threshold is
+/// crossed, and is then disarmed until re-armed by reset or, if there is a
+/// hysteresis, by the value going back past the threshold by at least that
+/// much, so that hovering around it does not spam.
+#[derive(Debug, Clone)]
+pub struct Triggers<T = u64> {
+    triggers: Vec<AlertTrigger<T>>,
+    armed: Vec<bool>,
+    direction: Direction,
+    hysteresis: Option<T>,
+}
+
+impl<T> Triggers<T>
+where
+    T: Copy + PartialOrd + std::ops::Add<Output = T>,
+{
+    pub fn new(
+        triggers: &[AlertTrigger<T>],
+        direction: Direction,
+        hysteresis: Option<T>,
+    ) -> Self {
+        Self {
+            triggers: triggers.to_vec(),
+            armed: vec![true; triggers.len()],
+            direction,
+            hysteresis,
+        }
+    }
+
+    /// Re-arms all.
+    pub fn reset(&mut self) {
+        self.armed.iter_mut().for_each(|armed| *armed = true);
+    }
+
+    fn is_crossed(&self, threshold: T, value: T) -> bool {
+        match self.direction {
+            Direction::Rising => value >= threshold,
+            Direction::Falling => value < threshold,
+        }
+    }
+
+    fn is_further(&self, a: T, b: T) -> bool {
+        match self.direction {
+            Direction::Rising => a > b,
+            Direction::Falling => a < b,
+        }
+    }
+
+    fn is_back(&self, threshold: T, value: T, hysteresis: T) -> bool {
+        match self.direction {
+            Direction::Rising => value + hysteresis < threshold,
+            Direction::Falling => value >= threshold + hysteresis,
+        }
+    }
+
+    /// The furthest crossed of the triggers which were just crossed, if
+    /// any, as that is the only one worth alerting about.
+    pub fn check(&mut self, value: T) -> Option<AlertTrigger<T>> {
+        let mut crossed: Option<AlertTrigger<T>> = None;
+        for (i, trigger) in self.triggers.iter().enumerate() {
+            let threshold = trigger.threshold;
+            if let Some(hysteresis) = self.hysteresis {
+                if self.is_back(threshold, value, hysteresis) {
+                    self.armed[i] = true;
+                }
+            }
+            if self.armed[i] && self.is_crossed(threshold, value) {
+                self.armed[i] = false;
+                if crossed
+                    .is_none_or(|c| self.is_further(threshold, c.threshold))
+                {
+                    crossed = Some(*trigger);
+                }
+            }
+        }
+        crossed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertTrigger, Direction, Level, Triggers};
+
+    #[test]
+    fn alert_trigger_parse() {
+        let trigger = |threshold, level| AlertTrigger { threshold, level };
+        assert_eq!(trigger(20, None), "20".parse().unwrap());
+        assert_eq!(trigger(20, Some(Level::Mid)), "20:mid".parse().unwrap());
+        assert_eq!(trigger(5, Some(Level::Hi)), "5:hi".parse().unwrap());
+        assert!("101".parse::<AlertTrigger>().is_err());
+        assert!("5:urgent".parse::<AlertTrigger>().is_err());
+        assert!(":hi".parse::<AlertTrigger>().is_err());
+        let trigger = |threshold, level| AlertTrigger { threshold, level };
+        assert_eq!(trigger(80.5, None), "80.5".parse().unwrap());
+        assert_eq!(
+            trigger(-10.0, Some(Level::Lo)),
+            "-10:lo".parse().unwrap()
+        );
+        assert!("hot".parse::<AlertTrigger<f32>>().is_err());
+    }
+
+    #[test]
+    fn alert_trigger_level() {
+        let level = |s: &str, direction| {
+            s.parse::<AlertTrigger>().unwrap().level(direction, 50, 90)
+        };
+        assert_eq!(Level::Lo, level("49", Direction::Rising));
+        assert_eq!(Level::Mid, level("50", Direction::Rising));
+        assert_eq!(Level::Hi, level("95", Direction::Rising));
+        assert_eq!(Level::Lo, level("95:lo", Direction::Rising));
+        let level = |s: &str| {
+            s.parse::<AlertTrigger>().unwrap().level(
+                Direction::Falling,
+                50,
+                25,
+            )
+        };
+        assert_eq!(Level::Lo, level("60"));
+        assert_eq!(Level::Mid, level("50"));
+        assert_eq!(Level::Hi, level("25"));
+        assert_eq!(Level::Mid, level("5:mid"));
+    }
+
+    #[test]
+    fn triggers_rising() {
+        let mut triggers = Triggers::new(
+            &[AlertTrigger::from(80), AlertTrigger::from(95)],
+            Direction::Rising,
+            Some(5),
+        );
+        let crossed: Vec<Option<u64>> =
+            [50, 80, 85, 97, 76, 80, 74, 99, 94, 95]
+                .into_iter()
+                .map(|pct| triggers.check(pct).map(|t| t.threshold))
+                .collect();
+        assert_eq!(
+            vec![
+                None,
+                Some(80),
+                None, // Already sent.
+                Some(95),
+                None, // Not back far enough to re-arm.
+                None,
+                None,     // Both re-armed.
+                Some(95), // Both at once, so just the highest.
+                None,
+                None,
+            ],
+            crossed
+        );
+    }
+
+    #[test]
+    fn triggers_falling() {
+        let mut triggers = Triggers::new(
+            &[AlertTrigger::from(25), AlertTrigger::from(10)],
+            Direction::Falling,
+            None,
+        );
+        let crossed: Vec<Option<u64>> = [50, 24, 30, 20, 5]
+            .into_iter()
+            .map(|pct| triggers.check(pct).map(|t| t.threshold))
+            .collect();
+        // Only re-armed by reset, without hysteresis.
+        assert_eq!(vec![None, Some(25), None, None, Some(10)], crossed);
+        triggers.reset();
+        assert_eq!(Some(10), triggers.check(5).map(|t| t.threshold));
+    }
+}
@@ -0,0 +1,222 @@
+// Changing the brightness, with the same rounding as the feed reads it, so
+// that the bar and whatever triggered the change agree on the percentage.
+
+use std::{
+    io::BufRead, // .lines()
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{Device, Selection};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Set(f32),
+    Inc(f32),
+    Dec(f32),
+}
+
+impl std::str::FromStr for Change {
+    type Err = anyhow::Error;
+
+    /// Like the subcommands: "set 50", "inc 5" or "dec 5".
+    fn from_str(s: &str) -> Result<Self> {
+        let (cmd, pct) = s
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| anyhow!("Invalid brightness change: {:?}", s))?;
+        let pct: f32 = pct
+            .trim()
+            .parse()
+            .context(format!("Invalid percentage in: {:?}", s))?;
+        match cmd {
+            "set" => Ok(Self::Set(pct)),
+            "inc" => Ok(Self::Inc(pct)),
+            "dec" => Ok(Self::Dec(pct)),
+            _ => Err(anyhow!("Invalid brightness command: {:?}", cmd)),
+        }
+    }
+}
+
+/// How percentages map to the raw brightness values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    /// Raw fraction is the perceived one raised to this. 1 is linear,
+    /// while around 4 makes equal steps look about equal to the eye.
+    pub exponent: f32,
+
+    /// Lowest percentage to go to, so that the screen is never turned off
+    /// by stepping down.
+    pub min: f32,
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self {
+            exponent: 1.0,
+            min: 0.0,
+        }
+    }
+}
+
+/// Raw brightness value to write, given the current and max ones.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation
+)]
+pub fn target(cur: u64, max: u64, change: Change, scale: &Scale) -> u64 {
+    let max_f = max as f32;
+    let to_perceived =
+        |raw: u64| (raw as f32 / max_f).powf(scale.exponent.recip()) * 100.0;
+    let to_raw = |pct: f32| {
+        let pct = pct.clamp(0.0, 100.0);
+        ((pct / 100.0).powf(scale.exponent) * max_f).round() as u64
+    };
+    let floor = ((scale.min.clamp(0.0, 100.0) / 100.0) * max_f).ceil() as u64;
+    let raw = match change {
+        Change::Set(pct) => to_raw(pct),
+        Change::Inc(step) => {
+            // At least one raw step, or low values may never get anywhere.
+            to_raw(to_perceived(cur) + step).max((cur + 1).min(max))
+        }
+        Change::Dec(step) => {
+            to_raw(to_perceived(cur) - step).min(cur.saturating_sub(1))
+        }
+    };
+    raw.clamp(floor.min(max), max)
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    fn set_brightness(
+        &self,
+        subsystem: &str,
+        name: &str,
+        brightness: u32,
+    ) -> zbus::Result<()>;
+}
+
+impl Device {
+    fn read_raw(&self, path: &Path) -> Result<u64> {
+        std::fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .context(format!("Invalid brightness in: {:?}", path))
+    }
+
+    fn subsystem(&self) -> &'static str {
+        if self.dir.starts_with(super::LEDS) {
+            "leds"
+        } else {
+            "backlight"
+        }
+    }
+
+    /// Through sysfs when permitted, otherwise through logind, which
+    /// permits it to whoever owns the active session.
+    fn write_raw(&self, value: u64) -> Result<()> {
+        match std::fs::write(self.dir.join("brightness"), value.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                tracing::debug!(dev = ?self, "Writing through logind.");
+                let conn = zbus::blocking::Connection::system()?;
+                SessionProxyBlocking::new(&conn)?.set_brightness(
+                    self.subsystem(),
+                    &self.name,
+                    u32::try_from(value)?,
+                )?;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the new percentage, as the feed would read it.
+    pub fn change(&self, change: Change, scale: &Scale) -> Result<u64> {
+        let max = self.read_raw(&self.dir.join("max_brightness"))?;
+        let cur = self.read_raw(&self.cur_path())?;
+        let new = target(cur, max, change, scale);
+        tracing::debug!(dev = ?self, cur, max, new, ?change, "Changing.");
+        if new != cur {
+            self.write_raw(new)?;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        crate::math::percentage_round(new as f32, max as f32)
+            .ok_or_else(|| anyhow!("Invalid brightness: {}/{}", new, max))
+    }
+}
+
+/// Change the first of the selected devices, which is the built-in panel,
+/// when auto-detected.
+pub fn change(
+    selection: &Selection,
+    change: Change,
+    scale: &Scale,
+) -> Result<u64> {
+    let devices = selection
+        .select(Path::new(super::BACKLIGHT), Path::new(super::LEDS))?;
+    let dev = devices
+        .first()
+        .ok_or_else(|| anyhow!("No backlight device found."))?;
+    dev.change(change, scale)
+}
+
+/// Between attempts to re-open the control FIFO, after failing to.
+const FIFO_RETRY: Duration = Duration::from_secs(5);
+
+fn create_fifo(fifo: &Path) -> Result<()> {
+    if !fifo.exists() {
+        nix::unistd::mkfifo(
+            fifo,
+            nix::sys::stat::Mode::S_IRUSR | nix::sys::stat::Mode::S_IWUSR,
+        )?;
+    }
+    Ok(())
+}
+
+/// Apply changes, one per line, as they are written to the given FIFO,
+/// which is created if it does not yet exist, or if it goes away later.
+pub fn listen(fifo: &Path, selection: Selection, scale: Scale) -> Result<()> {
+    create_fifo(fifo)?;
+    let fifo = fifo.to_path_buf();
+    std::thread::spawn(move || loop {
+        // Each writer closing it ends the lines, so we re-open for the next.
+        let file = match std::fs::File::open(&fifo) {
+            Ok(file) => file,
+            Err(error) => {
+                tracing::error!(
+                    ?fifo,
+                    ?error,
+                    ?FIFO_RETRY,
+                    "Failed to open control FIFO. Retrying."
+                );
+                std::thread::sleep(FIFO_RETRY);
+                if let Err(error) = create_fifo(&fifo) {
+                    tracing::error!(
+                        ?fifo,
+                        ?error,
+                        "Failed to re-create control FIFO."
+                    );
+                }
+                continue;
+            }
+        };
+        for line in std::io::BufReader::new(file).lines() {
+            let result = line
+                .map_err(anyhow::Error::from)
+                .and_then(|line| line.parse::<Change>())
+                .and_then(|c| change(&selection, c, &scale));
+            if let Err(error) = result {
+                tracing::error!(?error, "Failed to apply control command.");
+            }
+        }
+    });
+    Ok(())
+}
@@ -1,130 +1,384 @@
-use std::path::{Path, PathBuf};
+pub mod control;
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Result;
 
-#[derive(Debug)]
-struct Device {
-    max: PathBuf,
-    cur: PathBuf,
+pub const BACKLIGHT: &str = "/sys/class/backlight";
+pub const LEDS: &str = "/sys/class/leds";
+
+/// Values of the "type" attribute, in the order of preference, same as
+/// systemd-backlight: firmware (ACPI) interfaces are the most likely to do
+/// the right thing, while raw ones write straight to the GPU registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Firmware,
+    Platform,
+    Raw,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "firmware" => Ok(Self::Firmware),
+            "platform" => Ok(Self::Platform),
+            "raw" => Ok(Self::Raw),
+            _ => Err(anyhow::anyhow!("Unknown backlight type: {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    name: String,
+    dir: PathBuf,
 }
 
 impl Device {
-    pub fn new(name: &str) -> Self {
-        let base = "/sys/class/backlight/";
-        let max = [base, name, "max_brightness"].iter().collect();
-        let cur = [base, name, "brightness"].iter().collect();
-        Self { max, cur }
+    pub fn new(dir: &Path) -> Self {
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self {
+            name,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// What the hardware actually applied (which may differ from what was
+    /// last requested, like with ambient light sensors), if it tells us.
+    fn cur_path(&self) -> PathBuf {
+        let actual = self.dir.join("actual_brightness");
+        if actual.exists() {
+            actual
+        } else {
+            self.dir.join("brightness")
+        }
+    }
+
+    fn kind(&self) -> Result<Kind> {
+        std::fs::read_to_string(self.dir.join("type"))?
+            .trim()
+            .parse()
     }
 
     pub fn read_cur_brightness_pct(&self) -> Result<Option<u64>> {
-        let max: f32 = std::fs::read_to_string(&self.max)?.trim().parse()?;
-        let cur: f32 = std::fs::read_to_string(&self.cur)?.trim().parse()?;
+        let max = self.dir.join("max_brightness");
+        let cur = self.cur_path();
+        let max: f32 = std::fs::read_to_string(max)?.trim().parse()?;
+        let cur: f32 = std::fs::read_to_string(cur)?.trim().parse()?;
         Ok(crate::math::percentage_round(cur, max))
     }
 }
 
-struct Watcher {
-    dev: Device,
-    _watcher: notify::RecommendedWatcher, // XXX To keep it from being dropped.
-    receiver: std::sync::mpsc::Receiver<Result<notify::Event, notify::Error>>,
-}
-
-impl Watcher {
-    fn new(device_name: &str) -> Result<Self> {
-        let dev = Device::new(device_name);
-        tracing::info!(
-            "Instantiating new watcher for backlight device: {:?}",
-            &dev
-        );
-        let (sender, receiver) = std::sync::mpsc::channel();
-        let mut _watcher = notify::recommended_watcher(sender)?;
-        {
-            use notify::Watcher;
-            _watcher.watch(
-                Path::new(&dev.cur),
-                notify::RecursiveMode::Recursive,
-            )?;
+/// All devices of the class, sorted by name. Missing class directory is
+/// the same as no devices.
+fn list(class: &Path) -> Result<Vec<Device>> {
+    let entries = match std::fs::read_dir(class) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
         }
-        Ok(Self {
-            dev,
-            _watcher,
-            receiver,
+        Err(e) => return Err(e.into()),
+    };
+    let mut devices = entries
+        .map(|entry_result| {
+            entry_result.map(|entry| Device::new(&entry.path()))
         })
+        .collect::<Result<Vec<Device>, std::io::Error>>()?;
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// The backlight device which is most likely to be the one controlling the
+/// built-in panel.
+fn find_best(class: &Path) -> Result<Option<Device>> {
+    let best = list(class)?
+        .into_iter()
+        .filter_map(|dev| match dev.kind() {
+            Ok(kind) => Some((kind, dev)),
+            Err(error) => {
+                tracing::warn!(?dev, ?error, "Skipping backlight device.");
+                None
+            }
+        })
+        .min_by_key(|(kind, _)| *kind)
+        .map(|(_, dev)| dev);
+    tracing::debug!(?best, "Best backlight device.");
+    Ok(best)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Device names, from either the backlight or the leds class. Empty
+    /// means to pick the best backlight automatically.
+    pub devices: Vec<String>,
+
+    /// Also include keyboard backlights, from the leds class.
+    pub keyboard: bool,
+}
+
+impl Selection {
+    /// Devices, as currently found, since they can come and go (external
+    /// monitors, docks, USB keyboards, etc).
+    fn select(&self, backlight: &Path, leds: &Path) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        if self.devices.is_empty() {
+            devices.extend(find_best(backlight)?);
+        }
+        for name in &self.devices {
+            match [backlight, leds]
+                .iter()
+                .map(|class| class.join(name))
+                .find(|dir| dir.exists())
+            {
+                Some(dir) => devices.push(Device::new(&dir)),
+                None => tracing::warn!(?name, "Device not found."),
+            }
+        }
+        if self.keyboard {
+            devices.extend(
+                list(leds)?
+                    .into_iter()
+                    .filter(|dev| dev.name.ends_with("kbd_backlight")),
+            );
+        }
+        Ok(devices)
+    }
+}
+
+/// Several drivers do not notify of changes to their sysfs attributes, and
+/// actual_brightness never does, so unless told to poll from the start, we
+/// occasionally probe for changes which went unnoticed, and switch to
+/// polling once any are found.
+#[derive(Debug, Clone, Copy)]
+pub struct Polling {
+    pub always: bool,
+    pub interval: Duration,
+    pub probe_interval: Duration,
+}
+
+impl Default for Polling {
+    fn default() -> Self {
+        Self {
+            always: false,
+            interval: Duration::from_secs(1),
+            probe_interval: Duration::from_secs(10),
+        }
     }
+}
 
-    fn iter(&self) -> impl Iterator<Item = u64> + '_ {
-        use notify::event::{
-            DataChange, Event, EventKind::Modify, ModifyKind,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    name: String,
+    percentage: Option<u64>,
+}
+
+impl Reading {
+    /// Short enough for the bar, like "intel" of "intel_backlight" or "kbd"
+    /// of "tpacpi::kbd_backlight".
+    fn label(&self) -> &str {
+        let name = match self.name.rsplit_once("::") {
+            None => &self.name,
+            Some((_, function)) => function,
         };
+        name.strip_suffix("_backlight").unwrap_or(name)
+    }
+}
+
+struct Backlights {
+    selection: Selection,
+    backlight: PathBuf,
+    leds: PathBuf,
+    devices: Vec<Device>,
+    watcher: Option<crate::watch::Watcher>,
+    polling: Polling,
+    readings: Vec<Reading>,
+}
+
+impl Backlights {
+    fn new(
+        selection: Selection,
+        backlight: &Path,
+        leds: &Path,
+        polling: Polling,
+    ) -> Self {
+        Self {
+            selection,
+            backlight: backlight.to_path_buf(),
+            leds: leds.to_path_buf(),
+            devices: Vec::new(),
+            watcher: None,
+            polling,
+            readings: Vec::new(),
+        }
+    }
+
+    /// Re-select the devices and, if they changed, re-watch them. Called on
+    /// every tick, polled ones included, since sysfs does not notify of
+    /// devices being added to, or removed from, the class directories. So
+    /// those are noticed within the probe interval, unless told to poll.
+    /// Returns whether the devices changed.
+    fn refresh(&mut self) -> Result<bool> {
+        let devices = self.selection.select(&self.backlight, &self.leds)?;
+        if self.watcher.is_none() || devices != self.devices {
+            tracing::info!(?devices, "Watching backlight devices.");
+            let paths: Vec<PathBuf> = devices
+                .iter()
+                .map(|dev| dev.dir.join("brightness"))
+                .collect();
+            let interval = if self.polling.always {
+                self.polling.interval
+            } else {
+                self.polling.probe_interval
+            };
+            let mut watcher =
+                crate::watch::Watcher::new(&paths, Some(interval))?;
+            let _ = watcher.next(); // The immediate one is this refresh.
+            self.watcher = Some(watcher);
+            let changed = devices != self.devices;
+            self.devices = devices;
+            return Ok(changed);
+        }
+        Ok(false)
+    }
+
+    fn read(&self) -> Vec<Reading> {
+        self.devices
+            .iter()
+            .map(|dev| Reading {
+                name: dev.name.clone(),
+                percentage: dev.read_cur_brightness_pct().unwrap_or_else(
+                    |error| {
+                        tracing::error!(
+                            ?dev,
+                            ?error,
+                            "Failed to read backlight percentage."
+                        );
+                        None
+                    },
+                ),
+            })
+            .collect()
+    }
+}
 
-        // Dummy event to trigger initial reading:
-        std::iter::once(Ok(Event::new(Modify(ModifyKind::Data(
-            DataChange::Any,
-        )))))
-        .chain(self.receiver.iter())
-        .filter_map(|event_result| match event_result {
-            Ok(Event {
-                kind: Modify(ModifyKind::Data(DataChange::Any)),
-                ..
-            }) => match self.dev.read_cur_brightness_pct() {
-                Ok(None) => None,
-                Ok(Some(pct)) => Some(pct),
-                Err(err) => {
+impl Iterator for Backlights {
+    type Item = Vec<Reading>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let polled = match self.watcher.as_mut() {
+                None => false,
+                Some(watcher) => {
+                    watcher.next()?;
+                    watcher.polled()
+                }
+            };
+            let devices_changed = match self.refresh() {
+                Ok(changed) => changed,
+                Err(error) => {
                     tracing::error!(
-                        "Failed to read backlight percentage: {:?}",
-                        err
+                        ?error,
+                        "Failed to refresh backlight devices."
                     );
-                    None
+                    // Nothing to wait on, if we never got to watch anything.
+                    self.watcher.as_ref()?;
+                    false
                 }
-            },
-            Ok(_) => None,
-            Err(err) => {
-                tracing::error!("Watch event error: {:?}", err);
-                None
+            };
+            let readings = self.read();
+            if polled && readings == self.readings {
+                continue;
             }
-        })
+            // Devices coming and going are only ever noticed by polling.
+            if polled && !devices_changed && !self.polling.always {
+                tracing::warn!(
+                    devices = ?self.devices,
+                    "Change went unnoticed. Switching to polling."
+                );
+                self.polling.always = true;
+                self.watcher = None;
+                if let Err(error) = self.refresh() {
+                    tracing::error!(?error, "Failed to switch to polling.");
+                }
+            }
+            self.readings.clone_from(&readings);
+            return Some(readings);
+        }
     }
 }
 
 pub struct State<'a> {
     prefix: &'a str,
-    percentage: Option<u64>,
+    readings: Vec<Reading>,
 }
 
 impl<'a> State<'a> {
     pub fn new(prefix: &'a str) -> Self {
         Self {
             prefix,
-            percentage: None,
+            readings: Vec::new(),
         }
     }
 }
 
 impl<'a> crate::pipeline::State for State<'a> {
-    type Event = u64;
+    type Event = Vec<Reading>;
 
     fn update(
         &mut self,
-        percentage: Self::Event,
+        readings: Self::Event,
     ) -> Result<Option<Vec<crate::alert::Alert>>> {
-        self.percentage = Some(percentage);
+        self.readings = readings;
         Ok(None)
     }
 
     fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
         write!(buf, "{}", self.prefix)?;
-        match self.percentage {
-            None => write!(buf, "----")?,
-            Some(pct) => write!(buf, "{:3.0}%", pct)?,
+        if self.readings.is_empty() {
+            write!(buf, "----")?;
+        }
+        // Labeled only when there is more than one to tell apart.
+        let labeled = self.readings.len() > 1;
+        for (i, reading) in self.readings.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            if labeled {
+                write!(buf, "{} ", reading.label())?;
+            }
+            match reading.percentage {
+                None => write!(buf, "----")?,
+                Some(pct) => write!(buf, "{:3.0}%", pct)?,
+            }
         }
         writeln!(buf)?;
         Ok(())
     }
 }
 
-pub fn run(device: &str, prefix: &str) -> Result<()> {
+pub fn run(
+    selection: Selection,
+    polling: Polling,
+    prefix: &str,
+) -> Result<()> {
     crate::pipeline::run_to_stdout(
-        Watcher::new(device)?.iter(),
+        Backlights::new(
+            selection,
+            Path::new(BACKLIGHT),
+            Path::new(LEDS),
+            polling,
+        ),
         State::new(prefix),
     )
 }
@@ -0,0 +1,178 @@
+use std::{path::Path, time::Duration};
+
+use crate::test_util::TempDir;
+
+use super::{Backlights, Device, Polling, Reading, Selection};
+
+const BACKLIGHT: &str = "tests/sys-class-backlight";
+const LEDS: &str = "tests/sys-class-leds";
+
+fn reading(name: &str, percentage: u64) -> Reading {
+    Reading {
+        name: name.to_string(),
+        percentage: Some(percentage),
+    }
+}
+
+#[test]
+fn find_best() {
+    assert_eq!(
+        Some(Device::new(&Path::new(BACKLIGHT).join("acpi_video0"))),
+        super::find_best(Path::new(BACKLIGHT)).unwrap()
+    );
+    assert_eq!(None, super::find_best(Path::new(LEDS)).unwrap());
+    assert_eq!(
+        None,
+        super::find_best(Path::new("tests/nonexistent")).unwrap()
+    );
+}
+
+#[test]
+fn select_and_read() {
+    use crate::pipeline::State;
+
+    let mut backlights = Backlights::new(
+        Selection {
+            devices: vec![],
+            keyboard: true,
+        },
+        Path::new(BACKLIGHT),
+        Path::new(LEDS),
+        Polling::default(),
+    );
+    assert_eq!(
+        Some(vec![
+            reading("acpi_video0", 80),
+            reading("tpacpi::kbd_backlight", 50),
+        ]),
+        backlights.next()
+    );
+
+    let selection = Selection {
+        devices: vec![
+            "intel_backlight".to_string(),
+            "input3::capslock".to_string(),
+            "gone".to_string(),
+        ],
+        keyboard: false,
+    };
+    let devices = selection
+        .select(Path::new(BACKLIGHT), Path::new(LEDS))
+        .unwrap();
+    let mut backlights = Backlights::new(
+        selection,
+        Path::new(BACKLIGHT),
+        Path::new(LEDS),
+        Polling::default(),
+    );
+    assert_eq!(2, devices.len());
+    let readings = backlights.next().unwrap();
+    assert_eq!(
+        vec![
+            reading("intel_backlight", 50),
+            reading("input3::capslock", 0)
+        ],
+        readings
+    );
+
+    let mut state = super::State::new("b ");
+    let mut buf = Vec::new();
+    state.display(&mut buf).unwrap();
+    state.update(readings).unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "b ----\nb intel  50% capslock   0%\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn change_parse() {
+    use super::control::Change;
+
+    assert_eq!(Change::Set(50.0), "set 50".parse::<Change>().unwrap());
+    assert_eq!(Change::Inc(5.0), "inc 5\n".parse::<Change>().unwrap());
+    assert_eq!(Change::Dec(2.5), " dec  2.5".parse::<Change>().unwrap());
+    assert!("dec".parse::<Change>().is_err());
+    assert!("up 5".parse::<Change>().is_err());
+    assert!("inc five".parse::<Change>().is_err());
+}
+
+#[test]
+fn change_target() {
+    use super::control::{target, Change, Scale};
+
+    let linear = Scale::default();
+    assert_eq!(50, target(0, 100, Change::Set(50.0), &linear));
+    assert_eq!(100, target(97, 100, Change::Inc(5.0), &linear));
+    assert_eq!(0, target(3, 100, Change::Dec(5.0), &linear));
+    assert_eq!(9697, target(0, 19393, Change::Set(50.0), &linear));
+
+    let floored = Scale {
+        min: 1.0,
+        ..Scale::default()
+    };
+    assert_eq!(194, target(3000, 19393, Change::Dec(50.0), &floored));
+    assert_eq!(1, target(1, 15, Change::Dec(10.0), &floored));
+
+    let perceived = Scale {
+        exponent: 4.0,
+        min: 0.0,
+    };
+    // Half-way, to the eye, is only a 16th of the raw range.
+    assert_eq!(16, target(0, 256, Change::Set(50.0), &perceived));
+    assert_eq!(33, target(16, 256, Change::Inc(10.0), &perceived));
+    assert_eq!(7, target(16, 256, Change::Dec(10.0), &perceived));
+    // At least one raw step, even where the curve is flat.
+    assert_eq!(1, target(0, 256, Change::Inc(1.0), &perceived));
+    assert_eq!(0, target(1, 256, Change::Dec(1.0), &perceived));
+}
+
+#[test]
+fn change_device() {
+    use super::control::{Change, Scale};
+
+    let dir = TempDir::new("backlight");
+    std::fs::write(dir.join("max_brightness"), "15\n").unwrap();
+    std::fs::write(dir.join("brightness"), "12\n").unwrap();
+    let dev = Device::new(dir.path());
+    let scale = Scale::default();
+
+    assert_eq!(80, dev.read_cur_brightness_pct().unwrap().unwrap());
+    assert_eq!(47, dev.change(Change::Dec(33.0), &scale).unwrap());
+    assert_eq!(
+        "7",
+        std::fs::read_to_string(dir.join("brightness")).unwrap()
+    );
+    assert_eq!(47, dev.read_cur_brightness_pct().unwrap().unwrap());
+    assert_eq!(100, dev.change(Change::Set(120.0), &scale).unwrap());
+    assert_eq!(100, dev.read_cur_brightness_pct().unwrap().unwrap());
+}
+
+#[test]
+fn poll_when_unnoticed() {
+    let class = TempDir::new("backlight-poll");
+    let dir = class.join("amdgpu_bl0");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("type"), "raw\n").unwrap();
+    std::fs::write(dir.join("max_brightness"), "255\n").unwrap();
+    std::fs::write(dir.join("brightness"), "255\n").unwrap();
+    std::fs::write(dir.join("actual_brightness"), "255\n").unwrap();
+    let mut backlights = Backlights::new(
+        Selection::default(),
+        class.path(),
+        Path::new(LEDS),
+        Polling {
+            always: false,
+            interval: Duration::from_millis(10),
+            probe_interval: Duration::from_millis(50),
+        },
+    );
+    assert_eq!(Some(vec![reading("amdgpu_bl0", 100)]), backlights.next());
+    assert!(!backlights.polling.always);
+
+    // Ambient light sensor dimmed it, without any notification:
+    std::fs::write(dir.join("actual_brightness"), "51\n").unwrap();
+    assert_eq!(Some(vec![reading("amdgpu_bl0", 20)]), backlights.next());
+    assert!(backlights.polling.always);
+}
@@ -0,0 +1,281 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+pub const PROC_STAT: &str = "/proc/stat";
+
+/// From least to most busy.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Cumulative time spent in each state since boot, in USER_HZ ticks. Guest
+/// time is already included in user and nice, so is left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Times {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+
+impl Times {
+    fn total(&self) -> u64 {
+        self.user
+            + self.nice
+            + self.system
+            + self.idle
+            + self.iowait
+            + self.irq
+            + self.softirq
+            + self.steal
+    }
+
+    /// Waiting on I/O is idle too, since the CPU is free to do other work.
+    fn idle(&self) -> u64 {
+        self.idle + self.iowait
+    }
+
+    fn parse(fields: &[&str]) -> Result<Self> {
+        // Older kernels have fewer of the later fields.
+        let mut nums = [0; 8];
+        for (num, field) in nums.iter_mut().zip(fields) {
+            *num = field
+                .parse()
+                .map_err(|_| anyhow!("Invalid CPU time: {:?}", field))?;
+        }
+        let [user, nice, system, idle, iowait, irq, softirq, steal] = nums;
+        Ok(Self {
+            user,
+            nice,
+            system,
+            idle,
+            iowait,
+            irq,
+            softirq,
+            steal,
+        })
+    }
+}
+
+/// Of all CPUs together and of each online one, by its number, like 3 of
+/// "cpu3".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sample {
+    pub total: Times,
+    pub cores: BTreeMap<u32, Times>,
+}
+
+impl Sample {
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut total = None;
+        let mut cores = BTreeMap::new();
+        for line in data.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["cpu", ref times @ ..] => {
+                    total = Some(Times::parse(times)?);
+                }
+                [cpu, ref times @ ..] if cpu.starts_with("cpu") => {
+                    let n = cpu[3..].parse().map_err(|_| {
+                        anyhow!("Invalid CPU name: {:?}", cpu)
+                    })?;
+                    cores.insert(n, Times::parse(times)?);
+                }
+                _ => {}
+            }
+        }
+        let total = total.ok_or_else(|| anyhow!("No cpu line in stat"))?;
+        Ok(Self { total, cores })
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Percentages of time, between two samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub busy: u64,
+    pub iowait: u64,
+    pub steal: u64,
+}
+
+impl Usage {
+    /// None when no time passed, like for an offlined CPU.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn between(prev: &Times, cur: &Times) -> Option<Self> {
+        let total = cur.total().saturating_sub(prev.total());
+        if total == 0 {
+            return None;
+        }
+        let pct = |prev: u64, cur: u64| {
+            crate::math::percentage_round(
+                cur.saturating_sub(prev) as f32,
+                total as f32,
+            )
+        };
+        let busy =
+            total.saturating_sub(cur.idle().saturating_sub(prev.idle()));
+        Some(Self {
+            busy: pct(0, busy)?,
+            iowait: pct(prev.iowait, cur.iowait)?,
+            steal: pct(prev.steal, cur.steal)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reading {
+    total: Option<Usage>,
+    cores: BTreeMap<u32, Option<Usage>>,
+}
+
+impl Reading {
+    /// Cores which came or went (online) between the samples are left out.
+    pub fn between(prev: &Sample, cur: &Sample) -> Self {
+        Self {
+            total: Usage::between(&prev.total, &cur.total),
+            cores: cur
+                .cores
+                .iter()
+                .filter_map(|(n, cur)| {
+                    let prev = prev.cores.get(n)?;
+                    Some((*n, Usage::between(prev, cur)))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Field {
+    /// Percentage of all CPUs busy, like "42%".
+    Total,
+
+    /// A bar per core, like "▁▃▅█".
+    Cores,
+
+    /// Percentage of time waiting on I/O, like "w  3%".
+    Iowait,
+
+    /// Percentage of time stolen by the hypervisor, like "st  1%".
+    Steal,
+}
+
+fn bar(pct: u64) -> char {
+    let max = (BARS.len() - 1) as u64;
+    let i = (pct.min(100) * max + 50) / 100;
+    BARS[usize::try_from(i).unwrap_or_default()]
+}
+
+pub struct State<'a> {
+    prefix: &'a str,
+    fields: &'a [Field],
+    reading: Option<Reading>,
+}
+
+impl<'a> State<'a> {
+    pub fn new(prefix: &'a str, fields: &'a [Field]) -> Self {
+        Self {
+            prefix,
+            fields,
+            reading: None,
+        }
+    }
+}
+
+fn write_field<W: std::io::Write>(
+    mut buf: W,
+    field: Field,
+    reading: &Reading,
+) -> Result<()> {
+    let total = reading.total.as_ref();
+    match field {
+        Field::Total => match total {
+            None => write!(buf, "----")?,
+            Some(usage) => write!(buf, "{:3.0}%", usage.busy)?,
+        },
+        Field::Cores => {
+            for usage in reading.cores.values() {
+                match usage {
+                    None => write!(buf, " ")?,
+                    Some(usage) => write!(buf, "{}", bar(usage.busy))?,
+                }
+            }
+        }
+        Field::Iowait => match total {
+            None => write!(buf, "w ----")?,
+            Some(usage) => write!(buf, "w{:3.0}%", usage.iowait)?,
+        },
+        Field::Steal => match total {
+            None => write!(buf, "st ----")?,
+            Some(usage) => write!(buf, "st{:3.0}%", usage.steal)?,
+        },
+    }
+    Ok(())
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    /// None on the first tick, which only takes the first sample, so that
+    /// there is something, if only placeholders, to show from the start.
+    type Event = Option<Reading>;
+
+    fn update(
+        &mut self,
+        reading: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        self.reading = reading;
+        Ok(None)
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        write!(buf, "{}", self.prefix)?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match &self.reading {
+                None => write!(buf, "----")?,
+                Some(reading) => write_field(&mut buf, *field, reading)?,
+            }
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+/// Usage since the previous tick, so none on the first one, which only
+/// takes the first sample.
+fn reads(interval: Duration) -> impl Iterator<Item = Option<Reading>> {
+    use crate::clock;
+
+    let mut prev: Option<Sample> = None;
+    clock::new(interval).filter_map(move |clock::Tick| {
+        match Sample::read(Path::new(PROC_STAT)) {
+            Err(err) => {
+                tracing::error!("Failed to read CPU times: {:?}", err);
+                None
+            }
+            Ok(cur) => {
+                let reading =
+                    prev.as_ref().map(|prev| Reading::between(prev, &cur));
+                prev = Some(cur);
+                Some(reading)
+            }
+        }
+    })
+}
+
+pub fn run(prefix: &str, interval: Duration, fields: &[Field]) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(interval),
+        State::new(prefix, fields),
+    )
+}
@@ -0,0 +1,124 @@
+use super::{Field, Reading, Sample, Times, Usage};
+
+fn sample(path: &str) -> Sample {
+    Sample::parse(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn parse() {
+    let s = sample("tests/proc-stat-0.txt");
+    assert_eq!(4, s.cores.len());
+    assert_eq!(
+        Times {
+            user: 1000,
+            nice: 10,
+            system: 500,
+            idle: 8000,
+            iowait: 100,
+            irq: 20,
+            softirq: 30,
+            steal: 0,
+        },
+        s.cores[&0]
+    );
+    assert_eq!(4200, s.total.user);
+    // Older kernels have fewer fields.
+    let old = Sample::parse("cpu 1 2 3 4\ncpu0 1 2 3 4").unwrap();
+    assert_eq!(4, old.cores[&0].idle);
+    assert_eq!(0, old.cores[&0].steal);
+    assert!(Sample::parse("cpu0 1 2 3 4").is_err());
+    assert!(Sample::parse("cpu 1 2 x 4").is_err());
+}
+
+#[test]
+fn usage() {
+    let reading = Reading::between(
+        &sample("tests/proc-stat-0.txt"),
+        &sample("tests/proc-stat-1.txt"),
+    );
+    let usage = |busy, iowait, steal| {
+        Some(Usage {
+            busy,
+            iowait,
+            steal,
+        })
+    };
+    assert_eq!(usage(49, 8, 5), reading.total);
+    assert_eq!(
+        vec![
+            usage(90, 0, 0),
+            usage(5, 0, 0),
+            usage(40, 30, 0),
+            usage(60, 0, 20)
+        ],
+        reading.cores.into_values().collect::<Vec<_>>()
+    );
+    // No time passed.
+    let s = sample("tests/proc-stat-1.txt");
+    assert_eq!(None, Reading::between(&s, &s).total);
+
+    // Each core is compared with itself, even when another one went
+    // offline in between, and those not in both are left out.
+    let mut offlined = sample("tests/proc-stat-1.txt");
+    offlined.cores.remove(&2);
+    let reading =
+        Reading::between(&sample("tests/proc-stat-0.txt"), &offlined);
+    assert_eq!(
+        vec![0, 1, 3],
+        reading.cores.keys().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(usage(60, 0, 20), reading.cores[&3]);
+    let reading =
+        Reading::between(&offlined, &sample("tests/proc-stat-1.txt"));
+    assert_eq!(
+        vec![0, 1, 3],
+        reading.cores.keys().copied().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let fields = [Field::Total, Field::Cores, Field::Iowait, Field::Steal];
+    let mut state = super::State::new("c ", &fields);
+    let mut buf = Vec::new();
+    state.update(None).unwrap(); // First tick, only taking the first sample.
+    state.display(&mut buf).unwrap();
+    state
+        .update(Some(Reading::between(
+            &sample("tests/proc-stat-0.txt"),
+            &sample("tests/proc-stat-1.txt"),
+        )))
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    let mut partial = Reading::between(
+        &sample("tests/proc-stat-1.txt"),
+        &sample("tests/proc-stat-1.txt"),
+    );
+    partial.cores.insert(
+        1,
+        Some(Usage {
+            busy: 100,
+            iowait: 0,
+            steal: 0,
+        }),
+    );
+    state.update(Some(partial)).unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "c ---- ---- ---- ----\n\
+         c  49% ▇▁▄▅ w  8% st  5%\n\
+         c ----  █   w ---- st ----\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn bars() {
+    let bars: String = [0, 7, 8, 50, 92, 93, 100, 200]
+        .into_iter()
+        .map(super::bar)
+        .collect();
+    assert_eq!("▁▁▂▅▇███", bars);
+}
@@ -0,0 +1,259 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+pub const SYS_CPU: &str = "/sys/devices/system/cpu";
+
+/// Of a single CPU. Each attribute is missing where the driver does not
+/// support it, like EPP with acpi-cpufreq, or throttle counters on AMD.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cpu {
+    /// In kHz.
+    pub freq: Option<u64>,
+    pub governor: Option<String>,
+
+    /// Energy performance preference, like "balance_performance", which is
+    /// what power-profiles-daemon and the like actually set.
+    pub epp: Option<String>,
+
+    /// Times thermally throttled since boot, of the core and its package.
+    pub throttles: Option<u64>,
+}
+
+impl Cpu {
+    fn read(dir: &Path) -> Self {
+        let read = |path: &str| {
+            std::fs::read_to_string(dir.join(path))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        let num = |path: &str| read(path).and_then(|s| s.parse::<u64>().ok());
+        let throttles = [
+            "thermal_throttle/core_throttle_count",
+            "thermal_throttle/package_throttle_count",
+        ]
+        .into_iter()
+        .filter_map(num)
+        .fold(None, |sum: Option<u64>, n| Some(sum.unwrap_or(0) + n));
+        Self {
+            freq: num("cpufreq/scaling_cur_freq"),
+            governor: read("cpufreq/scaling_governor"),
+            epp: read("cpufreq/energy_performance_preference"),
+            throttles,
+        }
+    }
+}
+
+/// CPU directories, like cpu0, cpu1, etc, by number. Offline CPUs have no
+/// cpufreq attributes, so add nothing.
+fn cpu_dirs(sys_cpu: &Path) -> Result<BTreeMap<u32, PathBuf>> {
+    let mut dirs = BTreeMap::new();
+    for entry_result in std::fs::read_dir(sys_cpu)? {
+        let entry = entry_result?;
+        let name = entry.file_name();
+        if let Some(n) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("cpu"))
+            .and_then(|n| n.parse().ok())
+        {
+            dirs.insert(n, entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// By number, since CPUs can go offline and back online in between.
+    cpus: BTreeMap<u32, Cpu>,
+    time: Instant,
+}
+
+pub fn read(sys_cpu: &Path) -> Result<Reading> {
+    Ok(Reading {
+        cpus: cpu_dirs(sys_cpu)?
+            .into_iter()
+            .map(|(n, dir)| (n, Cpu::read(&dir)))
+            .collect(),
+        time: Instant::now(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Field {
+    /// Average frequency, like "2.0GHz".
+    Avg,
+
+    /// Highest frequency, like "3.1GHz".
+    Max,
+
+    /// Scaling governor, like "powersave".
+    Governor,
+
+    /// Energy performance preference, like "balance_performance".
+    Epp,
+
+    /// "!" when thermally throttled since the previous reading, or within
+    /// the hold time before it.
+    Throttle,
+}
+
+pub struct State<'a> {
+    prefix: &'a str,
+    fields: &'a [Field],
+    cpus: Option<BTreeMap<u32, Cpu>>,
+
+    /// Keep the throttle marker up for this long, so that it is not gone
+    /// before anyone gets to see it.
+    hold: Duration,
+    throttles: BTreeMap<u32, u64>,
+    throttled_at: Option<Instant>,
+    throttled: bool,
+}
+
+impl<'a> State<'a> {
+    pub fn new(prefix: &'a str, fields: &'a [Field], hold: Duration) -> Self {
+        Self {
+            prefix,
+            fields,
+            cpus: None,
+            hold,
+            throttles: BTreeMap::new(),
+            throttled_at: None,
+            throttled: false,
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn write_freq<W: std::io::Write>(mut buf: W, khz: Option<u64>) -> Result<()> {
+    match khz {
+        None => write!(buf, "----")?,
+        Some(khz) => write!(buf, "{:.1}GHz", khz as f64 / 1_000_000.0)?,
+    }
+    Ok(())
+}
+
+/// Distinct values, as there is normally just the one for all CPUs.
+fn write_names<'b, W: std::io::Write>(
+    mut buf: W,
+    names: impl Iterator<Item = &'b Option<String>>,
+) -> Result<()> {
+    let names: BTreeSet<&str> = names.flatten().map(String::as_str).collect();
+    if names.is_empty() {
+        write!(buf, "----")?;
+    } else {
+        let names: Vec<&str> = names.into_iter().collect();
+        write!(buf, "{}", names.join(","))?;
+    }
+    Ok(())
+}
+
+impl<'a> State<'a> {
+    fn write_field<W: std::io::Write>(
+        &self,
+        mut buf: W,
+        field: Field,
+        cpus: &BTreeMap<u32, Cpu>,
+    ) -> Result<()> {
+        let freqs = cpus.values().filter_map(|cpu| cpu.freq);
+        match field {
+            Field::Avg => {
+                let (sum, n) =
+                    freqs.fold((0, 0), |(sum, n), freq| (sum + freq, n + 1));
+                write_freq(buf, (n > 0).then(|| sum / n))?;
+            }
+            Field::Max => write_freq(buf, freqs.max())?,
+            Field::Governor => {
+                write_names(buf, cpus.values().map(|cpu| &cpu.governor))?;
+            }
+            Field::Epp => {
+                write_names(buf, cpus.values().map(|cpu| &cpu.epp))?;
+            }
+            Field::Throttle => {
+                write!(buf, "{}", if self.throttled { "!" } else { " " })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Reading;
+
+    fn update(
+        &mut self,
+        Reading { cpus, time }: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        let throttles: BTreeMap<u32, u64> = cpus
+            .iter()
+            .filter_map(|(n, cpu)| cpu.throttles.map(|t| (*n, t)))
+            .collect();
+        // Only of those which were there before, as others coming online
+        // bring their counts along.
+        let throttled: Vec<u32> = throttles
+            .iter()
+            .filter(|(n, curr)| {
+                self.throttles.get(n).is_some_and(|prev| *curr > prev)
+            })
+            .map(|(n, _)| *n)
+            .collect();
+        if !throttled.is_empty() {
+            tracing::info!(cpus = ?throttled, "Thermally throttled.");
+            self.throttled_at = Some(time);
+        }
+        self.throttled = self
+            .throttled_at
+            .is_some_and(|at| time.duration_since(at) <= self.hold);
+        self.throttles = throttles;
+        self.cpus = Some(cpus);
+        Ok(None)
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        write!(buf, "{}", self.prefix)?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match &self.cpus {
+                None => write!(buf, "----")?,
+                Some(cpus) => self.write_field(&mut buf, *field, cpus)?,
+            }
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+fn reads(interval: Duration) -> impl Iterator<Item = Reading> {
+    use crate::clock;
+    clock::new(interval).filter_map(|clock::Tick| {
+        match read(Path::new(SYS_CPU)) {
+            Err(err) => {
+                tracing::error!("Failed to read CPU frequencies: {:?}", err);
+                None
+            }
+            Ok(reading) => Some(reading),
+        }
+    })
+}
+
+pub fn run(
+    prefix: &str,
+    interval: Duration,
+    fields: &[Field],
+    hold: Duration,
+) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(interval),
+        State::new(prefix, fields, hold),
+    )
+}
@@ -0,0 +1,120 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use super::{Cpu, Field, Reading};
+
+fn read() -> Reading {
+    super::read(Path::new("tests/sys-devices-system-cpu")).unwrap()
+}
+
+#[test]
+fn cpus() {
+    let cpus = read().cpus;
+    assert_eq!(vec![0, 1, 2, 3], cpus.keys().copied().collect::<Vec<u32>>());
+    assert_eq!(
+        Cpu {
+            freq: Some(800_000),
+            governor: Some("powersave".to_string()),
+            epp: Some("balance_performance".to_string()),
+            throttles: Some(6 + 12),
+        },
+        cpus[&2]
+    );
+    // Not a CPU, so nothing there.
+    assert_eq!(
+        Cpu::default(),
+        Cpu::read(Path::new("tests/sys-devices-system-cpu/cpuidle"))
+    );
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let fields = [
+        Field::Avg,
+        Field::Max,
+        Field::Governor,
+        Field::Epp,
+        Field::Throttle,
+    ];
+    let mut state = super::State::new("f ", &fields, Duration::from_secs(5));
+    let start = Instant::now();
+    let at = |secs: u64| Reading {
+        time: start + Duration::from_secs(secs),
+        ..read()
+    };
+    let mut buf = Vec::new();
+    state.display(&mut buf).unwrap();
+    state.update(at(0)).unwrap();
+    state.display(&mut buf).unwrap();
+    // Throttled again, on another governor.
+    let mut reading = at(2);
+    let cpu1 = reading.cpus.get_mut(&1).unwrap();
+    cpu1.throttles = cpu1.throttles.map(|n| n + 1);
+    let cpu3 = reading.cpus.get_mut(&3).unwrap();
+    cpu3.governor = Some("performance".to_string());
+    cpu3.freq = None;
+    let throttled = reading.cpus.clone();
+    state.update(reading).unwrap();
+    state.display(&mut buf).unwrap();
+    // Not since, but still held.
+    state
+        .update(Reading {
+            cpus: throttled.clone(),
+            time: start + Duration::from_secs(7),
+        })
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    // No longer.
+    state
+        .update(Reading {
+            cpus: throttled,
+            time: start + Duration::from_secs(8),
+        })
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    state
+        .update(Reading {
+            cpus: [(0, Cpu::default())].into(),
+            time: start + Duration::from_secs(10),
+        })
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "f ---- ---- ---- ---- ----\n\
+         f 2.0GHz 3.1GHz powersave balance_performance  \n\
+         f 2.1GHz 3.1GHz performance,powersave balance_performance !\n\
+         f 2.1GHz 3.1GHz performance,powersave balance_performance !\n\
+         f 2.1GHz 3.1GHz performance,powersave balance_performance  \n\
+         f ---- ---- ---- ----  \n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn throttle_offline_online() {
+    use crate::pipeline::State;
+
+    let fields = [Field::Throttle];
+    let mut state = super::State::new("", &fields, Duration::ZERO);
+    let start = Instant::now();
+    let mut buf = Vec::new();
+    let mut update = |secs: u64, offline: &[u32]| {
+        let mut reading = Reading {
+            time: start + Duration::from_secs(secs),
+            ..read()
+        };
+        reading.cpus.retain(|n, _| !offline.contains(n));
+        state.update(reading).unwrap();
+        state.display(&mut buf).unwrap();
+    };
+    update(0, &[]);
+    // Neither the sum dropping, nor it coming back up, is throttling.
+    update(2, &[2]);
+    update(4, &[]);
+    update(6, &[0, 1]);
+    assert_eq!(" \n \n \n \n", String::from_utf8(buf).unwrap());
+}
@@ -0,0 +1,222 @@
+// Alerting on disks filling up, tracked per mount, since each fills up at
+// its own pace.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::alert::{self, Alert};
+
+use super::{Mount, Reading};
+
+/// Least, of how far back to look when estimating how fast a disk is
+/// filling, which is otherwise as far as the prediction reaches ahead, so
+/// that a single large write or delete does not swing it much.
+const RATE_WINDOW_MIN: Duration = Duration::from_secs(60);
+
+/// Usage percentage which, when reached, triggers an alert. Parsed from
+/// "<pct>[:<level>][@<mount>]", where mount is either the label or the path,
+/// like "95:hi@/home". Applies to all mounts, when none given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertTrigger {
+    pub trigger: alert::AlertTrigger,
+    pub mount: Option<String>,
+}
+
+impl AlertTrigger {
+    fn applies_to(&self, mount: &Mount) -> bool {
+        match &self.mount {
+            None => true,
+            Some(m) => m == &mount.path || Some(m) == mount.label.as_ref(),
+        }
+    }
+}
+
+/// Of the alert, when not given, by how full the disk is.
+pub fn level(trigger: &alert::AlertTrigger) -> alert::Level {
+    trigger.level(alert::Direction::Rising, 80, 95)
+}
+
+impl From<u64> for AlertTrigger {
+    fn from(threshold: u64) -> Self {
+        Self {
+            trigger: threshold.into(),
+            mount: None,
+        }
+    }
+}
+
+impl std::str::FromStr for AlertTrigger {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (trigger, mount) = match s.split_once('@') {
+            None => (s, None),
+            Some((_, "")) => {
+                return Err(anyhow!("Empty mount in alert trigger: {:?}", s))
+            }
+            Some((trigger, mount)) => (trigger, Some(mount.to_string())),
+        };
+        Ok(Self {
+            trigger: trigger.parse()?,
+            mount,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub triggers: Vec<AlertTrigger>,
+
+    /// Percentage points to drop below a threshold by, before its alert
+    /// can be triggered again, so that hovering around it does not spam.
+    pub hysteresis: u64,
+
+    /// Alert when, at the rate of growth over about as long (but at least
+    /// a minute), the disk would be full within this long.
+    pub fill_within: Option<Duration>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            triggers: Vec::new(),
+            hysteresis: 5,
+            fill_within: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Tracking {
+    /// Of those which apply to this mount.
+    triggers: alert::Triggers,
+
+    /// Bytes available to unprivileged users, as of when.
+    samples: VecDeque<(Instant, u64)>,
+
+    fill_sent: bool,
+}
+
+impl Tracking {
+    fn new(settings: &Settings, mount: &Mount) -> Self {
+        let triggers: Vec<alert::AlertTrigger> = settings
+            .triggers
+            .iter()
+            .filter(|t| t.applies_to(mount))
+            .map(|t| t.trigger)
+            .collect();
+        Self {
+            triggers: alert::Triggers::new(
+                &triggers,
+                alert::Direction::Rising,
+                Some(settings.hysteresis),
+            ),
+            samples: VecDeque::new(),
+            fill_sent: false,
+        }
+    }
+
+    /// Estimated time until full, if it is filling at all, and has been
+    /// watched for at least half the window, as less is not much to go by.
+    #[allow(clippy::cast_precision_loss)]
+    fn time_to_full(&self, window: Duration) -> Option<Duration> {
+        let (t0, avail0) = self.samples.front()?;
+        let (t1, avail1) = self.samples.back()?;
+        let elapsed = t1.duration_since(*t0);
+        if elapsed < window / 2 || avail1 >= avail0 {
+            return None;
+        }
+        let rate = (avail0 - avail1) as f64 / elapsed.as_secs_f64(); // B/s.
+                                                                     // Beyond what a Duration holds, when barely filling.
+        Duration::try_from_secs_f64(*avail1 as f64 / rate).ok()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Alerts {
+    settings: Settings,
+
+    /// By mount path.
+    tracking: HashMap<String, Tracking>,
+}
+
+impl Alerts {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            tracking: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, readings: &[Reading]) -> Vec<Alert> {
+        // Forget what was unmounted, so it starts afresh if it comes back.
+        self.tracking
+            .retain(|path, _| readings.iter().any(|r| &r.mount.path == path));
+        let mut alerts = Vec::new();
+        for reading in readings {
+            let Some(usage) = reading.usage else {
+                continue;
+            };
+            let Some(pct) = usage.used_pct() else {
+                continue;
+            };
+            let name =
+                reading.mount.label.as_ref().unwrap_or(&reading.mount.path);
+            let settings = &self.settings;
+            let tracking = self
+                .tracking
+                .entry(reading.mount.path.clone())
+                .or_insert_with(|| Tracking::new(settings, &reading.mount));
+
+            // Thresholds.
+            if let Some(trigger) = tracking.triggers.check(pct) {
+                alerts.push(Alert::new(
+                    level(&trigger),
+                    &format!("Disk {} is {}% full!", name, pct),
+                    &format!(
+                        "{} free of {}.",
+                        crate::units::fmt_bytes(usage.avail),
+                        crate::units::fmt_bytes(usage.total)
+                    ),
+                ));
+            }
+
+            // Rate of growth.
+            if let Some(within) = self.settings.fill_within {
+                let window = within.max(RATE_WINDOW_MIN);
+                tracking.samples.push_back((reading.time, usage.avail));
+                while let Some((t, _)) = tracking.samples.front() {
+                    if reading.time.duration_since(*t) > window {
+                        tracking.samples.pop_front();
+                    } else {
+                        break;
+                    }
+                }
+                match tracking.time_to_full(window) {
+                    Some(left) if left <= within => {
+                        if !tracking.fill_sent {
+                            tracking.fill_sent = true;
+                            alerts.push(Alert::new(
+                                alert::Level::Hi,
+                                &format!("Disk {} is filling fast!", name),
+                                &format!(
+                                    "Full in about {} minutes, at the \
+                                     current rate.",
+                                    left.as_secs().div_ceil(60)
+                                ),
+                            ));
+                        }
+                    }
+                    _ => {
+                        tracking.fill_sent = false;
+                    }
+                }
+            }
+        }
+        alerts
+    }
+}
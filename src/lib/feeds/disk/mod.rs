@@ -1,84 +1,288 @@
+pub mod alerts;
+pub mod mounts;
+
+#[cfg(test)]
+mod tests;
+
 use std::{
     ffi::{c_char, CString},
     mem::MaybeUninit,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
 
-fn usage(path: &str) -> Result<Option<u64>> {
+/// Sizes in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+
+    /// To unprivileged users, so without the blocks reserved for root.
+    pub avail: u64,
+
+    pub files: u64,
+    pub files_free: u64,
+}
+
+impl Usage {
+    /// Same as "df": of the space available to unprivileged users, since
+    /// that is what runs out first.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn used_pct(&self) -> Option<u64> {
+        let used = self.used as f32;
+        crate::math::percentage_ceiling(used, used + self.avail as f32)
+    }
+
+    /// Some filesystems (btrfs, etc) allocate inodes dynamically and report
+    /// none.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn files_used_pct(&self) -> Option<u64> {
+        if self.files == 0 {
+            return None;
+        }
+        let used = self.files.saturating_sub(self.files_free);
+        crate::math::percentage_ceiling(used as f32, self.files as f32)
+    }
+}
+
+#[allow(clippy::useless_conversion)] // Field types differ by platform.
+fn usage(path: &str) -> Result<Usage> {
     let path: CString = CString::new(path)?;
     let path: *const c_char = path.as_ptr();
     let mut buf: MaybeUninit<libc::statfs> = MaybeUninit::uninit();
     match unsafe { libc::statfs(path, buf.assume_init_mut()) } {
         0 => {
-            let (total, free) = unsafe {
-                let libc::statfs {
-                    f_blocks: total,
-                    f_bfree: free,
-                    ..
-                } = buf.assume_init();
-                (total, free)
-            };
-            let used = total - free;
-            let used_pct =
-                crate::math::percentage_ceiling(used as f32, total as f32);
-            Ok(used_pct)
+            let libc::statfs {
+                f_bsize,
+                f_frsize,
+                f_blocks,
+                f_bfree,
+                f_bavail,
+                f_files,
+                f_ffree,
+                ..
+            } = unsafe { buf.assume_init() };
+            // Blocks are counted in fragments, where those are supported.
+            let block =
+                u64::try_from(if f_frsize > 0 { f_frsize } else { f_bsize })?;
+            let blocks = |n| u64::from(n) * block;
+            Ok(Usage {
+                total: blocks(f_blocks),
+                used: blocks(f_blocks - f_bfree),
+                avail: blocks(f_bavail),
+                files: u64::from(f_files),
+                files_free: u64::from(f_ffree),
+            })
         }
         n => Err(anyhow!("libc::statfs failed with {}", n)),
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Field {
+    /// Percentage of space used, like "42%".
+    Pct,
+
+    /// Space available, like "12.3G free".
+    Free,
+
+    /// Space used, like "8.9G used".
+    Used,
+
+    /// Size, like "21.2G total".
+    Total,
+
+    /// Percentage of inodes used, like "i 3%".
+    Inodes,
+}
+
+/// Path to some file on the filesystem and, optionally, what to label it
+/// with. Parsed from "[<label>=]<path>", like "/" or "home=/home".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    pub label: Option<String>,
+    pub path: String,
+}
+
+impl std::str::FromStr for Mount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (label, path) = match s.split_once('=') {
+            None => (None, s),
+            Some((label, path)) => (Some(label.to_string()), path),
+        };
+        if path.is_empty() {
+            return Err(anyhow!("Empty path in: {:?}", s));
+        }
+        Ok(Self {
+            label,
+            path: path.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Selection {
+    Paths(Vec<Mount>),
+
+    /// Whatever is currently mounted, as it comes and goes.
+    Mounted(mounts::Filter),
+}
+
+impl Selection {
+    fn mounts(&self) -> Result<Vec<Mount>> {
+        match self {
+            Self::Paths(mounts) => Ok(mounts.clone()),
+            Self::Mounted(filter) => {
+                let data = std::fs::read_to_string(mounts::PROC_SELF_MOUNTS)?;
+                let mounts = filter
+                    .apply(mounts::parse(&data)?)
+                    .into_iter()
+                    .map(|entry| Mount {
+                        label: Some(entry.mountpoint.clone()),
+                        path: entry.mountpoint,
+                    })
+                    .collect();
+                Ok(mounts)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    mount: Mount,
+    usage: Option<Usage>,
+    time: Instant,
+}
+
 struct State<'a> {
     prefix: &'a str,
     postfix: &'a str,
-    usage: Option<u64>,
+    fields: &'a [Field],
+    alerts: alerts::Alerts,
+    readings: Vec<Reading>,
 }
 
 impl<'a> State<'a> {
-    fn new(prefix: &'a str, postfix: &'a str) -> Self {
+    fn new(
+        prefix: &'a str,
+        postfix: &'a str,
+        fields: &'a [Field],
+        alerts: alerts::Settings,
+    ) -> Self {
         Self {
             prefix,
             postfix,
-            usage: None,
+            fields,
+            alerts: alerts::Alerts::new(alerts),
+            readings: Vec::new(),
         }
     }
 }
 
+fn write_field<W: std::io::Write>(
+    mut buf: W,
+    field: Field,
+    usage: &Usage,
+) -> Result<()> {
+    use crate::units::fmt_bytes;
+
+    match (field, usage.used_pct(), usage.files_used_pct()) {
+        (Field::Pct, None, _) | (Field::Inodes, _, None) => {
+            write!(buf, "----")?;
+        }
+        (Field::Pct, Some(pct), _) => write!(buf, "{:3.0}%", pct)?,
+        (Field::Free, _, _) => {
+            write!(buf, "{} free", fmt_bytes(usage.avail))?
+        }
+        (Field::Used, _, _) => write!(buf, "{} used", fmt_bytes(usage.used))?,
+        (Field::Total, _, _) => {
+            write!(buf, "{} total", fmt_bytes(usage.total))?;
+        }
+        (Field::Inodes, _, Some(pct)) => write!(buf, "i{:3.0}%", pct)?,
+    }
+    Ok(())
+}
+
 impl<'a> crate::pipeline::State for State<'a> {
-    type Event = Option<u64>;
+    type Event = Vec<Reading>;
 
     fn update(
         &mut self,
         msg: Self::Event,
     ) -> Result<Option<Vec<crate::alert::Alert>>> {
-        self.usage = msg;
-        Ok(None)
+        let alerts = self.alerts.update(&msg);
+        self.readings = msg;
+        Ok((!alerts.is_empty()).then_some(alerts))
     }
 
     fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
         write!(buf, "{}", self.prefix)?;
-        match self.usage {
-            None => write!(buf, "----")?,
-            Some(pct) => write!(buf, "{:3.0}%", pct)?,
+        // A lone path needs no label, unless given one.
+        let multiple = self.readings.len() > 1;
+        for (i, Reading { mount, usage, .. }) in
+            self.readings.iter().enumerate()
+        {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match &mount.label {
+                Some(label) => write!(buf, "{} ", label)?,
+                None if multiple => write!(buf, "{} ", mount.path)?,
+                None => {}
+            }
+            for (j, field) in self.fields.iter().enumerate() {
+                if j > 0 {
+                    write!(buf, " ")?;
+                }
+                match usage {
+                    None => write!(buf, "----")?,
+                    Some(usage) => write_field(&mut buf, *field, usage)?,
+                }
+            }
         }
         writeln!(buf, "{}", self.postfix)?;
         Ok(())
     }
 }
 
+fn read(mounts: Vec<Mount>) -> Vec<Reading> {
+    mounts
+        .into_iter()
+        .map(|mount| {
+            let usage = usage(&mount.path)
+                .map_err(|err| {
+                    tracing::error!(
+                        "Failed to read disk usage of {:?}: {:?}",
+                        &mount.path,
+                        err
+                    );
+                })
+                .ok();
+            Reading {
+                mount,
+                usage,
+                time: Instant::now(),
+            }
+        })
+        .collect()
+}
+
 fn reads(
     interval: Duration,
-    path: &str,
-) -> impl Iterator<Item = Option<u64>> + '_ {
+    selection: &Selection,
+) -> impl Iterator<Item = Vec<Reading>> + '_ {
     use crate::clock;
 
-    clock::new(interval).filter_map(|clock::Tick| match usage(path) {
+    clock::new(interval).filter_map(|clock::Tick| match selection.mounts() {
         Err(err) => {
-            tracing::error!("Failed to read disk usage: {:?}", err);
+            tracing::error!("Failed to select disks: {:?}", err);
             None
         }
-        Ok(usage_opt) => Some(usage_opt),
+        Ok(mounts) => Some(read(mounts)),
     })
 }
 
@@ -86,10 +290,12 @@ pub fn run<'a>(
     prefix: &'a str,
     postfix: &'a str,
     interval: Duration,
-    path: &'a str,
+    selection: &'a Selection,
+    fields: &'a [Field],
+    alerts: alerts::Settings,
 ) -> Result<()> {
     crate::pipeline::run_to_stdout(
-        reads(interval, path),
-        State::new(prefix, postfix),
+        reads(interval, selection),
+        State::new(prefix, postfix, fields, alerts),
     )
 }
@@ -0,0 +1,150 @@
+// Mounted filesystems, as listed in /proc/self/mounts (same format as
+// fstab(5)), so that we can find the real ones without being told.
+
+use anyhow::{anyhow, Result};
+
+pub const PROC_SELF_MOUNTS: &str = "/proc/self/mounts";
+
+/// Which are not backed by any storage we could run out of.
+const VIRTUAL: [&str; 28] = [
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    // Read-only images (snaps, etc), which are always full:
+    "squashfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+    "fuse.gvfsd-fuse",
+    "fuse.portal",
+    "fuse.snapfuse",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub source: String,
+    pub mountpoint: String,
+    pub fstype: String,
+}
+
+/// Spaces, tabs, etc are octal-escaped, like "\040".
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        unescaped.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4);
+        match code.and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                unescaped.push(char::from(byte));
+                rest = &rest[i + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+pub fn parse(data: &str) -> Result<Vec<Entry>> {
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(
+            |line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [source, mountpoint, fstype, ..] => Ok(Entry {
+                    source: unescape(source),
+                    mountpoint: unescape(mountpoint),
+                    fstype: fstype.to_string(),
+                }),
+                _ => Err(anyhow!("Invalid mounts line: {:?}", line)),
+            },
+        )
+        .collect()
+}
+
+/// Shell-style: "*" matches any run of characters (including "/") and
+/// "?" matches any single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the latest star eat one more character.
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only these filesystem types. All real ones, if empty.
+    pub fstypes: Vec<String>,
+
+    /// Only mountpoints matching any of these globs. All, if empty.
+    pub mountpoints: Vec<String>,
+}
+
+impl Filter {
+    /// Bind mounts (and btrfs subvolumes) of the same source are the same
+    /// space, so only the first mountpoint of each is kept.
+    pub fn apply(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        let mut sources = std::collections::HashSet::new();
+        entries
+            .into_iter()
+            .filter(|e| {
+                if self.fstypes.is_empty() {
+                    !VIRTUAL.contains(&e.fstype.as_str())
+                } else {
+                    self.fstypes.contains(&e.fstype)
+                }
+            })
+            .filter(|e| {
+                self.mountpoints.is_empty()
+                    || self
+                        .mountpoints
+                        .iter()
+                        .any(|glob| glob_match(glob, &e.mountpoint))
+            })
+            .filter(|e| sources.insert(e.source.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,269 @@
+use std::time::{Duration, Instant};
+
+use crate::alert;
+
+use super::{
+    alerts::{self, AlertTrigger, Settings},
+    mounts::{self, glob_match, Filter},
+    Field, Mount, Reading, Usage,
+};
+
+fn mountpoints(filter: &Filter) -> Vec<String> {
+    let data = std::fs::read_to_string("tests/proc-self-mounts.txt").unwrap();
+    filter
+        .apply(mounts::parse(&data).unwrap())
+        .into_iter()
+        .map(|entry| entry.mountpoint)
+        .collect()
+}
+
+#[test]
+fn glob() {
+    assert!(glob_match("/", "/"));
+    assert!(glob_match("/mnt/*", "/mnt/nas"));
+    assert!(glob_match("/media/*", "/media/user/USB STICK"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("/mnt/???", "/mnt/nas"));
+    assert!(glob_match("*a*a*", "/mnt/data"));
+    assert!(!glob_match("/mnt/*", "/mnt"));
+    assert!(!glob_match("/mnt/??", "/mnt/nas"));
+    assert!(!glob_match("/home", "/home/user"));
+}
+
+#[test]
+fn discover() {
+    assert_eq!(
+        vec![
+            "/",
+            "/boot/efi",
+            "/mnt/data",
+            "/mnt/nas",
+            "/media/user/USB STICK"
+        ],
+        mountpoints(&Filter::default())
+    );
+    assert_eq!(
+        vec!["/mnt/data", "/mnt/nas"],
+        mountpoints(&Filter {
+            fstypes: vec![],
+            mountpoints: vec!["/mnt/*".to_string()],
+        })
+    );
+    assert_eq!(
+        vec!["/", "/mnt/data"],
+        mountpoints(&Filter {
+            fstypes: vec!["btrfs".to_string(), "ext4".to_string()],
+            mountpoints: vec![],
+        })
+    );
+}
+
+#[test]
+fn mount_parse() {
+    assert_eq!(
+        Mount {
+            label: None,
+            path: "/".to_string()
+        },
+        "/".parse::<Mount>().unwrap()
+    );
+    assert_eq!(
+        Mount {
+            label: Some("home".to_string()),
+            path: "/home".to_string()
+        },
+        "home=/home".parse::<Mount>().unwrap()
+    );
+    assert!("home=".parse::<Mount>().is_err());
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    const G: u64 = 1024 * 1024 * 1024;
+    let usage = Usage {
+        total: 110 * G,
+        used: 42 * G,
+        avail: 58 * G, // The rest is reserved.
+        files: 1000,
+        files_free: 970,
+    };
+    let reading = |mount: &str, usage| Reading {
+        mount: mount.parse().unwrap(),
+        usage,
+        time: Instant::now(),
+    };
+    let mut state =
+        super::State::new("d ", "|", &[Field::Pct], Settings::default());
+    let mut buf = Vec::new();
+    state.update(vec![reading("/", Some(usage))]).unwrap();
+    state.display(&mut buf).unwrap();
+    state
+        .update(vec![
+            reading("/", Some(usage)),
+            reading("nas=/mnt/nas", None),
+        ])
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "d  42%|\nd /  42% nas ----|\n",
+        String::from_utf8(buf).unwrap()
+    );
+
+    let fields = [
+        Field::Free,
+        Field::Used,
+        Field::Total,
+        Field::Pct,
+        Field::Inodes,
+    ];
+    let mut state = super::State::new("d ", "", &fields, Settings::default());
+    let mut buf = Vec::new();
+    state.update(vec![reading("/", Some(usage))]).unwrap();
+    state.display(&mut buf).unwrap();
+    state
+        .update(vec![reading(
+            "/",
+            Some(Usage {
+                files: 0,
+                files_free: 0,
+                ..usage
+            }),
+        )])
+        .unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "d 58.0G free 42.0G used 110.0G total  42% i  3%\n\
+         d 58.0G free 42.0G used 110.0G total  42% ----\n",
+        String::from_utf8(buf).unwrap()
+    );
+
+    let readings = super::read(vec!["/".parse().unwrap()]);
+    assert!(readings[0].usage.is_some());
+}
+
+#[test]
+fn alert_trigger_parse() {
+    use crate::alert::Level;
+
+    let trigger = |threshold, level, mount: Option<&str>| AlertTrigger {
+        trigger: alert::AlertTrigger { threshold, level },
+        mount: mount.map(String::from),
+    };
+    assert_eq!(trigger(80, None, None), "80".parse().unwrap());
+    assert_eq!(
+        trigger(95, Some(Level::Hi), Some("/home")),
+        "95:hi@/home".parse().unwrap()
+    );
+    assert_eq!(trigger(90, None, Some("nas")), "90@nas".parse().unwrap());
+    assert_eq!(Level::Mid, alerts::level(&AlertTrigger::from(80).trigger));
+    assert_eq!(Level::Hi, alerts::level(&AlertTrigger::from(95).trigger));
+    assert!("101".parse::<AlertTrigger>().is_err());
+    assert!("90@".parse::<AlertTrigger>().is_err());
+    assert!("90:urgent".parse::<AlertTrigger>().is_err());
+}
+
+#[test]
+fn alerts() {
+    use crate::pipeline::State;
+
+    const G: u64 = 1024 * 1024 * 1024;
+    let start = Instant::now();
+    let reading = |mount: &str, used: u64, secs: u64| Reading {
+        mount: mount.parse().unwrap(),
+        usage: Some(Usage {
+            total: 100 * G,
+            used: used * G,
+            avail: (100 - used) * G,
+            files: 0,
+            files_free: 0,
+        }),
+        time: start + Duration::from_secs(secs),
+    };
+
+    let settings = Settings {
+        triggers: vec![
+            AlertTrigger::from(80),
+            AlertTrigger::from(95),
+            "50@/home".parse().unwrap(),
+        ],
+        hysteresis: 5,
+        fill_within: None,
+    };
+    let mut state = super::State::new("d ", "", &[Field::Pct], settings);
+    let mut update = |used: &[u64]| {
+        let readings = ["/", "/home"]
+            .iter()
+            .zip(used)
+            .map(|(mount, used)| reading(mount, *used, 0))
+            .collect();
+        state.update(readings).unwrap().map_or(0, |a| a.len())
+    };
+    let counts: Vec<usize> = [
+        [40, 40],
+        [79, 49],
+        [80, 50], // Both cross.
+        [81, 51], // Already sent.
+        [76, 46], // Not below the hysteresis margin yet.
+        [80, 50],
+        [74, 44], // Re-armed.
+        [97, 50], // Both thresholds at once, so just the highest.
+        [96, 50],
+        [89, 50], // 95 re-armed, 80 not.
+        [95, 50],
+    ]
+    .iter()
+    .map(|used| update(used))
+    .collect();
+    assert_eq!(vec![0, 0, 2, 0, 0, 0, 0, 2, 0, 0, 1], counts);
+
+    let settings = Settings {
+        fill_within: Some(Duration::from_secs(10 * 60)),
+        ..Settings::default()
+    };
+    let mut state = super::State::new("d ", "", &[Field::Pct], settings);
+    let counts: Vec<usize> = [
+        (50, 0),
+        (50, 60),
+        (60, 120), // 10G/2m, but not watched for long enough to tell.
+        (60, 300), // 10G/5m, with 40G left.
+        (60, 360),
+        (60, 600),  // 10G/10m.
+        (70, 660),  // 20G/10m, with 30G left.
+        (80, 720),  // 20G/10m, with 20G left.
+        (85, 780),  // Still filling, already sent.
+        (85, 1500), // Stopped.
+    ]
+    .iter()
+    .map(|(used, secs)| {
+        state
+            .update(vec![reading("/", *used, *secs)])
+            .unwrap()
+            .map_or(0, |a| a.len())
+    })
+    .collect();
+    assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0], counts);
+
+    // Barely filling, with so much left, it is longer than a Duration.
+    let settings = Settings {
+        fill_within: Some(Duration::from_secs(10 * 60)),
+        ..Settings::default()
+    };
+    let mut state = super::State::new("d ", "", &[Field::Pct], settings);
+    let huge = |avail: u64, secs: u64| Reading {
+        usage: Some(Usage {
+            total: u64::MAX,
+            used: u64::MAX - avail,
+            avail,
+            files: 0,
+            files_free: 0,
+        }),
+        ..reading("/", 0, secs)
+    };
+    assert!(state.update(vec![huge(1 << 63, 0)]).unwrap().is_none());
+    assert!(state
+        .update(vec![huge((1 << 63) - 1, 600)])
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,321 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+pub const PROC_DISKSTATS: &str = "/proc/diskstats";
+pub const SYS_BLOCK: &str = "/sys/block";
+
+/// Regardless of the device's actual sector size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Cumulative counters since boot, of a single block device.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub read: u64,
+    pub written: u64,
+
+    /// Milliseconds spent doing I/O, with any number of requests in flight.
+    pub busy: u64,
+}
+
+/// Device names with their stats, in the order listed.
+pub fn parse(data: &str) -> Result<Vec<(String, Stats)>> {
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Only the first 14 fields are there on all kernel versions.
+            if fields.len() < 14 {
+                return Err(anyhow!("Invalid diskstats line: {:?}", line));
+            }
+            let name = fields[2];
+            let [sectors_read, sectors_written, ms_busy] =
+                [fields[5], fields[9], fields[12]];
+            let num = |field: &str| -> Result<u64> {
+                field
+                    .parse()
+                    .context(format!("Invalid diskstats line: {:?}", line))
+            };
+            let stats = Stats {
+                read: num(sectors_read)? * SECTOR_SIZE,
+                written: num(sectors_written)? * SECTOR_SIZE,
+                busy: num(ms_busy)?,
+            };
+            Ok((name.to_string(), stats))
+        })
+        .collect()
+}
+
+/// Whole, physical disks: those backed by a device, which leaves out
+/// partitions (not listed in /sys/block) and virtual devices (loop, dm,
+/// md, zram, etc), which would otherwise be counted twice in the total.
+pub fn physical(sys_block: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry_result in std::fs::read_dir(sys_block)? {
+        let entry = entry_result?;
+        if entry.path().join("device").exists() {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Per second, between two samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rates {
+    pub read: u64,
+    pub write: u64,
+
+    /// Percentage of time the device was busy, like %util of iostat.
+    pub util: u64,
+}
+
+impl Rates {
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation
+    )]
+    pub fn between(prev: &Stats, cur: &Stats, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        // Counters reset when a device is re-attached.
+        let rate = |prev: u64, cur: u64| {
+            (cur.saturating_sub(prev) as f64 / secs).round() as u64
+        };
+        let busy = cur.busy.saturating_sub(prev.busy) as f32;
+        let util = crate::math::percentage_ceiling(
+            busy.min(elapsed.as_millis() as f32),
+            elapsed.as_millis() as f32,
+        );
+        Self {
+            read: rate(prev.read, cur.read),
+            write: rate(prev.written, cur.written),
+            util: util.unwrap_or(0),
+        }
+    }
+
+    /// Of all the devices together: throughput adds up, while busy is that
+    /// of the busiest one.
+    fn total<'a>(rates: impl Iterator<Item = &'a Rates>) -> Option<Self> {
+        rates.fold(None, |total, r| match total {
+            None => Some(*r),
+            Some(Self { read, write, util }) => Some(Self {
+                read: read + r.read,
+                write: write + r.write,
+                util: util.max(r.util),
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Device names, as in /proc/diskstats, including partitions. Empty
+    /// means all physical disks.
+    pub devices: Vec<String>,
+}
+
+impl Selection {
+    fn select(&self, sys_block: &Path) -> Result<Vec<String>> {
+        if self.devices.is_empty() {
+            physical(sys_block)
+        } else {
+            Ok(self.devices.clone())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    name: String,
+
+    /// None on the first sample, since rates need two.
+    rates: Option<Rates>,
+}
+
+struct Sample {
+    time: Instant,
+    stats: HashMap<String, Stats>,
+}
+
+impl Sample {
+    fn read(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(Self {
+            time: Instant::now(),
+            stats: parse(&data)?.into_iter().collect(),
+        })
+    }
+
+    /// Of the named devices, which are there. Those which are not are
+    /// added to missing, and warned about only once they go missing,
+    /// rather than on every sample.
+    fn readings(
+        &self,
+        prev: Option<&Self>,
+        names: &[String],
+        missing: &mut HashSet<String>,
+    ) -> Vec<Reading> {
+        names
+            .iter()
+            .filter_map(|name| {
+                let Some(cur) = self.stats.get(name) else {
+                    if missing.insert(name.clone()) {
+                        tracing::warn!(
+                            ?name,
+                            "Device not found in diskstats."
+                        );
+                    }
+                    return None;
+                };
+                if missing.remove(name) {
+                    tracing::info!(?name, "Device found in diskstats.");
+                }
+                let rates = prev.and_then(|prev| {
+                    prev.stats.get(name).map(|prev_stats| {
+                        let elapsed = self.time.duration_since(prev.time);
+                        Rates::between(prev_stats, cur, elapsed)
+                    })
+                });
+                Some(Reading {
+                    name: name.clone(),
+                    rates,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct State<'a> {
+    prefix: &'a str,
+    per_device: bool,
+    util: bool,
+    readings: Vec<Reading>,
+}
+
+impl<'a> State<'a> {
+    pub fn new(prefix: &'a str, per_device: bool, util: bool) -> Self {
+        Self {
+            prefix,
+            per_device,
+            util,
+            readings: Vec::new(),
+        }
+    }
+
+    fn write_rates<W: std::io::Write>(
+        &self,
+        mut buf: W,
+        rates: Option<&Rates>,
+    ) -> Result<()> {
+        use crate::units::fmt_bytes;
+
+        match rates {
+            None => write!(buf, "----")?,
+            Some(r) => {
+                write!(
+                    buf,
+                    "r {}/s w {}/s",
+                    fmt_bytes(r.read),
+                    fmt_bytes(r.write)
+                )?;
+                if self.util {
+                    write!(buf, " {:3.0}%", r.util)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Vec<Reading>;
+
+    fn update(
+        &mut self,
+        readings: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        self.readings = readings;
+        Ok(None)
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        write!(buf, "{}", self.prefix)?;
+        if self.per_device {
+            for (i, reading) in self.readings.iter().enumerate() {
+                if i > 0 {
+                    write!(buf, " ")?;
+                }
+                write!(buf, "{} ", reading.name)?;
+                self.write_rates(&mut buf, reading.rates.as_ref())?;
+            }
+        } else {
+            // Of those with rates, so that one just plugged in does not
+            // blank out the others.
+            let total = Rates::total(
+                self.readings.iter().filter_map(|r| r.rates.as_ref()),
+            );
+            self.write_rates(&mut buf, total.as_ref())?;
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+fn reads<'a>(
+    interval: Duration,
+    selection: &'a Selection,
+    diskstats: &'a Path,
+    sys_block: &'a Path,
+) -> impl Iterator<Item = Vec<Reading>> + 'a {
+    use crate::clock;
+
+    let mut prev: Option<Sample> = None;
+    let mut missing = HashSet::new();
+    clock::new(interval).filter_map(move |clock::Tick| {
+        let names = selection
+            .select(sys_block)
+            .map_err(|err| {
+                tracing::error!("Failed to select block devices: {:?}", err);
+            })
+            .ok()?;
+        match Sample::read(diskstats) {
+            Err(err) => {
+                tracing::error!("Failed to read diskstats: {:?}", err);
+                None
+            }
+            Ok(sample) => {
+                let readings =
+                    sample.readings(prev.as_ref(), &names, &mut missing);
+                prev = Some(sample);
+                Some(readings)
+            }
+        }
+    })
+}
+
+pub fn run(
+    prefix: &str,
+    interval: Duration,
+    selection: &Selection,
+    per_device: bool,
+    util: bool,
+) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(
+            interval,
+            selection,
+            Path::new(PROC_DISKSTATS),
+            Path::new(SYS_BLOCK),
+        ),
+        State::new(prefix, per_device, util),
+    )
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::HashSet,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use super::{Rates, Reading, Sample, Stats};
+
+fn sample(start: Instant, secs: u64, data: &str) -> Sample {
+    Sample {
+        time: start + Duration::from_secs(secs),
+        stats: super::parse(data).unwrap().into_iter().collect(),
+    }
+}
+
+#[test]
+fn parse() {
+    let data = std::fs::read_to_string("tests/proc-diskstats.txt").unwrap();
+    let stats = super::parse(&data).unwrap();
+    assert_eq!(10, stats.len());
+    assert_eq!(
+        (
+            "nvme0n1".to_string(),
+            Stats {
+                read: 24_823_790 * 512,
+                written: 60_455_304 * 512,
+                busy: 712_604,
+            }
+        ),
+        stats[2]
+    );
+    // Older kernels have fewer fields.
+    assert_eq!(
+        (
+            "sdb".to_string(),
+            Stats {
+                read: 12040 * 512,
+                written: 0,
+                busy: 130,
+            }
+        ),
+        stats[9]
+    );
+    assert!(super::parse("8 0 sda 1 2 3").is_err());
+    assert!(super::parse("8 0 sda 1 2 x 4 5 6 7 8 9 10 11").is_err());
+}
+
+#[test]
+fn physical() {
+    assert_eq!(
+        vec!["nvme0n1", "sda", "sdb"],
+        super::physical(Path::new("tests/sys-block")).unwrap()
+    );
+}
+
+#[test]
+fn rates() {
+    let prev = Stats {
+        read: 0,
+        written: 1000,
+        busy: 5000,
+    };
+    let cur = Stats {
+        read: 4 * 1024 * 1024,
+        written: 1000,
+        busy: 5500,
+    };
+    assert_eq!(
+        Rates {
+            read: 2 * 1024 * 1024,
+            write: 0,
+            util: 25,
+        },
+        Rates::between(&prev, &cur, Duration::from_secs(2))
+    );
+    // Device re-attached, so counters started over.
+    assert_eq!(
+        Rates {
+            read: 0,
+            write: 0,
+            util: 0
+        },
+        Rates::between(&cur, &prev, Duration::from_secs(2))
+    );
+    // Busy for longer than elapsed, due to timer granularity.
+    assert_eq!(
+        100,
+        Rates::between(&prev, &cur, Duration::from_millis(400)).util
+    );
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let names = vec!["nvme0n1".to_string(), "sda".to_string()];
+    let start = Instant::now();
+    let s0 = sample(
+        start,
+        0,
+        "259 0 nvme0n1 0 0 0 0 0 0 0 0 0 0 0\n8 0 sda 0 0 0 0 0 0 0 0 0 0 0",
+    );
+    let s1 = sample(
+        start,
+        5,
+        "259 0 nvme0n1 0 0 20480 0 0 0 2048 0 0 500 0\n\
+         8 0 sda 0 0 10240 0 0 0 0 0 0 4000 0",
+    );
+    let mut missing = HashSet::new();
+    let first = s0.readings(None, &names, &mut missing);
+    assert!(first.iter().all(|Reading { rates, .. }| rates.is_none()));
+    let second = s1.readings(Some(&s0), &names, &mut missing);
+    assert!(missing.is_empty());
+    let names_sdb = [names.clone(), vec!["sdb".to_string()]].concat();
+    assert_eq!(2, s1.readings(None, &names_sdb, &mut missing).len());
+    assert!(missing.contains("sdb"));
+    // Plugged in.
+    let s2 = sample(start, 10, "8 16 sdb 0 0 0 0 0 0 0 0 0 0 0");
+    assert_eq!(1, s2.readings(None, &names_sdb, &mut missing).len());
+    assert_eq!(HashSet::from(["nvme0n1".into(), "sda".into()]), missing);
+
+    let mut buf = Vec::new();
+    let mut state = super::State::new("io ", false, true);
+    state.update(first.clone()).unwrap();
+    state.display(&mut buf).unwrap();
+    state.update(second.clone()).unwrap();
+    state.display(&mut buf).unwrap();
+    // Just plugged in, so no rates of its own yet.
+    let mut plugged = second.clone();
+    plugged.push(Reading {
+        name: "sdb".to_string(),
+        rates: None,
+    });
+    state.update(plugged).unwrap();
+    state.display(&mut buf).unwrap();
+    let mut state = super::State::new("io ", true, false);
+    state.update(first).unwrap();
+    state.display(&mut buf).unwrap();
+    state.update(second).unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "io ----\n\
+         io r 3.0M/s w 204.8K/s  80%\n\
+         io r 3.0M/s w 204.8K/s  80%\n\
+         io nvme0n1 ---- sda ----\n\
+         io nvme0n1 r 2.0M/s w 204.8K/s sda r 1.0M/s w 0B/s\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
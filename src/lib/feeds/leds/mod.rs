@@ -0,0 +1,270 @@
+// Keyboard backlight and indicator LEDs (caps lock, mic mute, etc).
+//
+// Ref: <https://www.kernel.org/doc/html/latest/leds/leds-class.html>
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use super::backlight::{self, LEDS};
+
+pub const DEFAULT_INDICATORS: [(&str, &str); 6] = [
+    ("kbd_backlight", "⌨"),
+    ("capslock", "⇪"),
+    ("numlock", "⇭"),
+    ("scrolllock", "⇳"),
+    ("micmute", "🎙"),
+    ("mute", "🔇"),
+];
+
+/// LED function (the part of the device name after the last "::", like
+/// "capslock" in "input3::capslock") and the symbol to show it with.
+/// Parsed from "<function>=<symbol>".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indicator {
+    pub function: String,
+    pub symbol: String,
+}
+
+impl Indicator {
+    /// Keyboard backlights have levels, other LEDs are just on or off.
+    fn is_level(&self) -> bool {
+        self.function.ends_with("kbd_backlight")
+    }
+}
+
+impl From<(&str, &str)> for Indicator {
+    fn from((function, symbol): (&str, &str)) -> Self {
+        Self {
+            function: function.to_string(),
+            symbol: symbol.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Indicator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('=') {
+            Some((function, symbol)) if !function.is_empty() => {
+                Ok(Self::from((function, symbol)))
+            }
+            _ => Err(anyhow!("Invalid LED indicator: {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Level(Option<u64>),
+    On(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    symbol: String,
+    value: Value,
+}
+
+/// Device directories, sorted, by function. Missing class directory is the
+/// same as no devices, since it only appears along with the first of them.
+fn list(dir: &Path, function: &str) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut devices = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.rsplit("::").next() == Some(function) {
+            devices.push(path);
+        }
+    }
+    devices.sort();
+    Ok(devices)
+}
+
+fn read_pct(dir: &Path) -> Option<u64> {
+    backlight::Device::new(dir)
+        .read_cur_brightness_pct()
+        .unwrap_or_else(|error| {
+            tracing::error!(?dir, ?error, "Failed to read LED brightness.");
+            None
+        })
+}
+
+/// Statuses of the indicators which are present, along with the device
+/// directories they were read from. The same function can be provided by
+/// several devices (like caps lock of each keyboard), so any of them being
+/// on is on.
+fn read(
+    dir: &Path,
+    indicators: &[Indicator],
+) -> Result<(Vec<PathBuf>, Vec<Status>)> {
+    let mut dirs = Vec::new();
+    let mut statuses = Vec::new();
+    for indicator in indicators {
+        let devices = list(dir, &indicator.function)?;
+        if devices.is_empty() {
+            continue;
+        }
+        let pcts: Vec<Option<u64>> =
+            devices.iter().map(|dev| read_pct(dev)).collect();
+        let value = if indicator.is_level() {
+            Value::Level(pcts.iter().flatten().max().copied())
+        } else {
+            Value::On(pcts.iter().flatten().any(|pct| *pct > 0))
+        };
+        statuses.push(Status {
+            symbol: indicator.symbol.clone(),
+            value,
+        });
+        dirs.extend(devices);
+    }
+    Ok((dirs, statuses))
+}
+
+struct Leds {
+    dir: PathBuf,
+    indicators: Vec<Indicator>,
+    interval: Duration,
+    devices: Vec<PathBuf>,
+    watcher: Option<crate::watch::Watcher>,
+    statuses: Option<Vec<Status>>,
+}
+
+impl Leds {
+    fn new(dir: &Path, indicators: &[Indicator], interval: Duration) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            indicators: indicators.to_vec(),
+            interval,
+            devices: Vec::new(),
+            watcher: None,
+            statuses: None,
+        }
+    }
+
+    /// Re-watch, if devices came or went (like a keyboard being plugged).
+    /// Which is only noticed by polling, since sysfs does not notify of
+    /// changes to the class directory.
+    fn rewatch(&mut self, devices: Vec<PathBuf>) -> Result<()> {
+        if self.watcher.is_none() || devices != self.devices {
+            tracing::info!(?devices, "Watching LED devices.");
+            // Most do not notify of changes, hence also polling.
+            let paths: Vec<PathBuf> =
+                devices.iter().map(|dev| dev.join("brightness")).collect();
+            let mut watcher =
+                crate::watch::Watcher::new(&paths, Some(self.interval))?;
+            let _ = watcher.next(); // The immediate one is the current read.
+            self.watcher = Some(watcher);
+            self.devices = devices;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Leds {
+    type Item = Vec<Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(watcher) = self.watcher.as_mut() {
+                watcher.next()?;
+            }
+            let result = read(&self.dir, &self.indicators).and_then(
+                |(devices, statuses)| {
+                    self.rewatch(devices).map(|()| statuses)
+                },
+            );
+            match result {
+                Err(error) => {
+                    tracing::error!(?error, "Failed to read LEDs.");
+                    // Nothing to wait on, if we never got to watch anything.
+                    self.watcher.as_ref()?;
+                }
+                // Polling would otherwise repeat the same line.
+                Ok(statuses) if Some(&statuses) == self.statuses.as_ref() => {
+                }
+                Ok(statuses) => {
+                    self.statuses = Some(statuses.clone());
+                    return Some(statuses);
+                }
+            }
+        }
+    }
+}
+
+pub struct State<'a> {
+    prefix: &'a str,
+    statuses: Vec<Status>,
+}
+
+impl<'a> State<'a> {
+    pub fn new(prefix: &'a str) -> Self {
+        Self {
+            prefix,
+            statuses: Vec::new(),
+        }
+    }
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Vec<Status>;
+
+    fn update(
+        &mut self,
+        statuses: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        self.statuses = statuses;
+        Ok(None)
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        write!(buf, "{}", self.prefix)?;
+        if self.statuses.is_empty() {
+            write!(buf, "----")?;
+        }
+        for (i, Status { symbol, value }) in self.statuses.iter().enumerate()
+        {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match value {
+                Value::Level(None) => write!(buf, "{}----", symbol)?,
+                Value::Level(Some(pct)) => {
+                    write!(buf, "{}{:3.0}%", symbol, pct)?;
+                }
+                Value::On(true) => write!(buf, "{}", symbol)?,
+                // Blank, rather than nothing, to keep the others in place.
+                Value::On(false) => {
+                    write!(buf, "{}", " ".repeat(symbol.chars().count()))?;
+                }
+            }
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+pub fn run(
+    indicators: &[Indicator],
+    interval: Duration,
+    prefix: &str,
+) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        Leds::new(Path::new(LEDS), indicators, interval),
+        State::new(prefix),
+    )
+}
@@ -0,0 +1,83 @@
+use std::{path::Path, time::Duration};
+
+use super::{Indicator, Leds, Status, Value, DEFAULT_INDICATORS};
+
+const FIXTURE: &str = "tests/sys-class-leds";
+
+fn status(symbol: &str, value: Value) -> Status {
+    Status {
+        symbol: symbol.to_string(),
+        value,
+    }
+}
+
+#[test]
+fn indicator_parse() {
+    assert_eq!(
+        Indicator::from(("capslock", "A")),
+        "capslock=A".parse::<Indicator>().unwrap()
+    );
+    assert_eq!(
+        Indicator::from(("mute", "")),
+        "mute=".parse::<Indicator>().unwrap()
+    );
+    assert!("capslock".parse::<Indicator>().is_err());
+    assert!("=A".parse::<Indicator>().is_err());
+}
+
+#[test]
+fn read_and_display() {
+    use crate::pipeline::State;
+
+    let indicators: Vec<Indicator> =
+        DEFAULT_INDICATORS.map(Indicator::from).to_vec();
+    let mut leds =
+        Leds::new(Path::new(FIXTURE), &indicators, Duration::from_secs(60));
+    let statuses = leds.next().unwrap();
+    assert_eq!(
+        vec![
+            status("⌨", Value::Level(Some(50))),
+            status("⇪", Value::On(true)), // input17, but not input3.
+            status("⇭", Value::On(false)),
+            status("🎙", Value::On(true)),
+        ],
+        statuses
+    );
+    assert_eq!(
+        vec![
+            Path::new(FIXTURE).join("tpacpi::kbd_backlight"),
+            Path::new(FIXTURE).join("input17::capslock"),
+            Path::new(FIXTURE).join("input3::capslock"),
+            Path::new(FIXTURE).join("input3::numlock"),
+            Path::new(FIXTURE).join("platform::micmute"),
+        ],
+        leds.devices
+    );
+
+    let mut state = super::State::new("");
+    let mut buf = Vec::new();
+    state.update(statuses).unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!("⌨ 50% ⇪   🎙\n", String::from_utf8(buf).unwrap());
+}
+
+#[test]
+fn missing() {
+    use crate::pipeline::State;
+
+    let indicators: Vec<Indicator> =
+        DEFAULT_INDICATORS.map(Indicator::from).to_vec();
+    let mut leds = Leds::new(
+        Path::new("tests/sys-class-leds-missing"),
+        &indicators,
+        Duration::from_secs(60),
+    );
+    assert_eq!(Some(vec![]), leds.next());
+    // Still polling, for any to appear.
+    assert!(leds.watcher.is_some());
+
+    let mut state = super::State::new("l ");
+    let mut buf = Vec::new();
+    state.display(&mut buf).unwrap();
+    assert_eq!("l ----\n", String::from_utf8(buf).unwrap());
+}
@@ -0,0 +1,259 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::alert::{self, Alert};
+
+pub const PROC_LOADAVG: &str = "/proc/loadavg";
+pub const PROC_UPTIME: &str = "/proc/uptime";
+pub const SYS_CPU_ONLINE: &str = "/sys/devices/system/cpu/online";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadAvg {
+    /// Over the last 1, 5 and 15 minutes.
+    pub loads: [f32; 3],
+
+    /// Tasks (threads) currently runnable, and all of them.
+    pub running: u64,
+    pub total: u64,
+}
+
+impl LoadAvg {
+    pub fn parse(data: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid loadavg: {:?}", data);
+        match data.split_whitespace().collect::<Vec<&str>>()[..] {
+            [l1, l5, l15, tasks, _last_pid] => {
+                let (running, total) =
+                    tasks.split_once('/').ok_or_else(invalid)?;
+                let load = |s: &str| s.parse().map_err(|_| invalid());
+                let tasks = |s: &str| s.parse().map_err(|_| invalid());
+                Ok(Self {
+                    loads: [load(l1)?, load(l5)?, load(l15)?],
+                    running: tasks(running)?,
+                    total: tasks(total)?,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Time since boot, including any spent suspended.
+pub fn parse_uptime(data: &str) -> Result<Duration> {
+    let secs: f64 = data
+        .split_whitespace()
+        .next()
+        .and_then(|secs| secs.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid uptime: {:?}", data))?;
+    Duration::try_from_secs_f64(secs)
+        .map_err(|e| anyhow!("Invalid uptime: {:?}: {}", data, e))
+}
+
+/// Number of CPUs in a list like "0-3,6,8-9", which is the format of
+/// /sys/devices/system/cpu/online. Which, unlike available_parallelism,
+/// is not limited by the affinity or the cgroup quota of the process, so
+/// matches what the load is averaged over.
+pub fn parse_cpu_list(data: &str) -> Result<usize> {
+    let invalid = || anyhow!("Invalid CPU list: {:?}", data);
+    data.trim()
+        .split(',')
+        .map(|range| {
+            let num = |s: &str| s.parse::<usize>().map_err(|_| invalid());
+            match range.split_once('-') {
+                None => num(range).map(|_| 1),
+                Some((first, last)) => {
+                    let (first, last) = (num(first)?, num(last)?);
+                    last.checked_sub(first).map(|n| n + 1).ok_or_else(invalid)
+                }
+            }
+        })
+        .sum()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    loadavg: LoadAvg,
+    uptime: Duration,
+    cpus: usize,
+    time: Instant,
+}
+
+fn read(loadavg: &Path, uptime: &Path, online: &Path) -> Result<Reading> {
+    Ok(Reading {
+        loadavg: LoadAvg::parse(&std::fs::read_to_string(loadavg)?)?,
+        uptime: parse_uptime(&std::fs::read_to_string(uptime)?)?,
+        cpus: parse_cpu_list(&std::fs::read_to_string(online)?)?,
+        time: Instant::now(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Field {
+    /// 1-minute load, like "3.42".
+    Load,
+
+    /// 1, 5 and 15-minute loads, like "3.42 2.17 1.05".
+    Loads,
+
+    /// Runnable and all tasks, like "5/1289".
+    Tasks,
+
+    /// Time since boot, like "up 4d 3h".
+    Uptime,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub prefix: String,
+    pub fields: Vec<Field>,
+
+    /// Display loads divided by the number of CPUs, so that 1.00 is all of
+    /// them busy, however many there are.
+    pub per_cpu: bool,
+
+    /// Alert when the 1-minute load per CPU stays above this ...
+    pub alert_load: Option<f32>,
+
+    /// ... for this long.
+    pub alert_for: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            fields: vec![Field::Load],
+            per_cpu: false,
+            alert_load: None,
+            alert_for: Duration::from_secs(60),
+        }
+    }
+}
+
+struct State<'a> {
+    settings: &'a Settings,
+    reading: Option<Reading>,
+
+    /// When the load per CPU went above the alert threshold.
+    high_since: Option<Instant>,
+    high_sent: bool,
+}
+
+impl<'a> State<'a> {
+    fn new(settings: &'a Settings) -> Self {
+        Self {
+            settings,
+            reading: None,
+            high_since: None,
+            high_sent: false,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn alert_load(&mut self, reading: &Reading) -> Option<Alert> {
+        let threshold = self.settings.alert_load?;
+        let load = reading.loadavg.loads[0] / reading.cpus as f32;
+        if load <= threshold {
+            self.high_since = None;
+            self.high_sent = false;
+            return None;
+        }
+        let since = *self.high_since.get_or_insert(reading.time);
+        let duration = reading.time.duration_since(since);
+        if self.high_sent || duration < self.settings.alert_for {
+            return None;
+        }
+        self.high_sent = true;
+        let summary = format!("Load above {:.2} per CPU!", threshold);
+        let body = format!(
+            "{:.2} on {} CPUs, for {}.",
+            reading.loadavg.loads[0],
+            reading.cpus,
+            crate::units::fmt_duration(duration)
+        );
+        Some(Alert::new(alert::Level::Mid, &summary, &body))
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn write_field<W: std::io::Write>(
+    mut buf: W,
+    field: Field,
+    reading: &Reading,
+    per_cpu: bool,
+) -> Result<()> {
+    let divisor = if per_cpu { reading.cpus as f32 } else { 1.0 };
+    let loads = reading.loadavg.loads.map(|load| load / divisor);
+    match field {
+        Field::Load => write!(buf, "{:.2}", loads[0])?,
+        Field::Loads => {
+            write!(buf, "{:.2} {:.2} {:.2}", loads[0], loads[1], loads[2])?;
+        }
+        Field::Tasks => write!(
+            buf,
+            "{}/{}",
+            reading.loadavg.running, reading.loadavg.total
+        )?,
+        Field::Uptime => {
+            write!(buf, "up {}", crate::units::fmt_duration(reading.uptime))?
+        }
+    }
+    Ok(())
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Reading;
+
+    fn update(&mut self, reading: Self::Event) -> Result<Option<Vec<Alert>>> {
+        let alert = self.alert_load(&reading);
+        self.reading = Some(reading);
+        Ok(alert.map(|alert| vec![alert]))
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        write!(buf, "{}", self.settings.prefix)?;
+        for (i, field) in self.settings.fields.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match &self.reading {
+                None => write!(buf, "----")?,
+                Some(reading) => write_field(
+                    &mut buf,
+                    *field,
+                    reading,
+                    self.settings.per_cpu,
+                )?,
+            }
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+fn reads(interval: Duration) -> impl Iterator<Item = Reading> {
+    use crate::clock;
+    clock::new(interval).filter_map(|clock::Tick| {
+        match read(
+            Path::new(PROC_LOADAVG),
+            Path::new(PROC_UPTIME),
+            Path::new(SYS_CPU_ONLINE),
+        ) {
+            Err(err) => {
+                tracing::error!("Failed to read load: {:?}", err);
+                None
+            }
+            Ok(reading) => Some(reading),
+        }
+    })
+}
+
+pub fn run(settings: &Settings, interval: Duration) -> Result<()> {
+    crate::pipeline::run_to_stdout(reads(interval), State::new(settings))
+}
@@ -0,0 +1,119 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use super::{Field, LoadAvg, Reading, Settings};
+
+#[test]
+fn parse() {
+    assert_eq!(
+        LoadAvg {
+            loads: [3.42, 2.17, 1.05],
+            running: 5,
+            total: 1289,
+        },
+        LoadAvg::parse(
+            &std::fs::read_to_string("tests/proc-loadavg.txt").unwrap()
+        )
+        .unwrap()
+    );
+    assert!(LoadAvg::parse("3.42 2.17 1.05 5 402117").is_err());
+    assert!(LoadAvg::parse("3.42 2.17 1.05").is_err());
+    assert_eq!(
+        Duration::from_secs_f64(356_521.83),
+        super::parse_uptime(
+            &std::fs::read_to_string("tests/proc-uptime.txt").unwrap()
+        )
+        .unwrap()
+    );
+    assert!(super::parse_uptime("").is_err());
+    assert!(super::parse_uptime("-1.00 2.00").is_err());
+    assert!(super::parse_uptime("inf 2.00").is_err());
+    assert_eq!(7, super::parse_cpu_list("0-3,6,8-9\n").unwrap());
+    assert_eq!(1, super::parse_cpu_list("0\n").unwrap());
+    assert!(super::parse_cpu_list("").is_err());
+    assert!(super::parse_cpu_list("3-0").is_err());
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let reading = super::read(
+        Path::new("tests/proc-loadavg.txt"),
+        Path::new("tests/proc-uptime.txt"),
+        Path::new("tests/sys-devices-system-cpu/online"),
+    )
+    .unwrap();
+    assert_eq!(4, reading.cpus);
+    let mut buf = Vec::new();
+    for (per_cpu, reading) in [
+        (false, None),
+        (false, Some(&reading)),
+        (true, Some(&reading)),
+    ] {
+        let settings = Settings {
+            prefix: "l ".to_string(),
+            fields: vec![
+                Field::Load,
+                Field::Loads,
+                Field::Tasks,
+                Field::Uptime,
+            ],
+            per_cpu,
+            ..Settings::default()
+        };
+        let mut state = super::State::new(&settings);
+        if let Some(reading) = reading {
+            state.update(reading.clone()).unwrap();
+        }
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(
+        "l ---- ---- ---- ----\n\
+         l 3.42 3.42 2.17 1.05 5/1289 up 4d 3h\n\
+         l 0.86 0.86 0.54 0.26 5/1289 up 4d 3h\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn alert_sustained_load() {
+    use crate::pipeline::State;
+
+    let start = Instant::now();
+    let reading = |load: f32, secs: u64| Reading {
+        loadavg: LoadAvg {
+            loads: [load, 0.0, 0.0],
+            running: 1,
+            total: 100,
+        },
+        uptime: Duration::ZERO,
+        cpus: 4,
+        time: start + Duration::from_secs(secs),
+    };
+    let settings = Settings {
+        alert_load: Some(1.5),
+        alert_for: Duration::from_secs(60),
+        ..Settings::default()
+    };
+    let mut state = super::State::new(&settings);
+    let counts: Vec<usize> = [
+        (4.0, 0),
+        (7.0, 10),  // 1.75 per CPU.
+        (8.0, 60),  // Not for long enough yet.
+        (9.0, 70),  // Now.
+        (9.0, 200), // Already sent.
+        (5.0, 210), // Back to below.
+        (7.0, 220),
+        (7.0, 290),
+    ]
+    .iter()
+    .map(|(load, secs)| {
+        let alerts = state.update(reading(*load, *secs)).unwrap();
+        alerts.map_or(0, |a| a.len())
+    })
+    .collect();
+    assert_eq!(vec![0, 0, 0, 1, 0, 0, 0, 1], counts);
+}
@@ -1,122 +1,379 @@
-use std::{
-    io::BufRead, // .lines()
-    time::Duration,
-};
+pub mod oom;
+
+#[cfg(test)]
+mod tests;
+
+use std::{path::Path, time::Duration};
 
 use anyhow::{anyhow, Result};
 
-struct Info {
-    total: u64,
-    available: u64,
+use crate::alert::{self, Alert};
+
+pub const PROC_MEMINFO: &str = "/proc/meminfo";
+
+/// Only there when the kernel is built with CONFIG_PSI.
+pub const PROC_PRESSURE_MEMORY: &str = "/proc/pressure/memory";
+
+/// Sizes in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Info {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
 }
 
 impl Info {
-    fn read() -> Result<Self> {
-        let path = "/proc/meminfo";
-        let file = std::fs::File::open(path)?;
-        let reader = std::io::BufReader::new(file);
-        let mut total_quant = None;
-        let mut avail_quant = None;
-        let mut total_units = None;
-        let mut avail_units = None;
-        for line_result in reader.lines() {
-            match (total_quant, avail_quant) {
-                (Some(_), Some(_)) => break,
-                (_, _) => {
-                    let line = line_result?;
-                    match line.split_whitespace().collect::<Vec<&str>>()[..] {
-                        ["MemTotal:", qty, units] => {
-                            total_quant = qty.parse().ok();
-                            total_units = Some(units.to_string());
-                        }
-                        ["MemAvailable:", qty, units] => {
-                            avail_quant = qty.parse().ok();
-                            avail_units = Some(units.to_string());
-                        }
-                        _ => (),
-                    }
-                }
-            }
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut total = None;
+        let mut available = None;
+        let mut swap_total = None;
+        let mut swap_free = None;
+        for line in data.lines() {
+            let (field, kb) =
+                match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                    [field, qty, "kB"] => (field, qty),
+                    _ => continue,
+                };
+            let dst = match field {
+                "MemTotal:" => &mut total,
+                "MemAvailable:" => &mut available,
+                "SwapTotal:" => &mut swap_total,
+                "SwapFree:" => &mut swap_free,
+                _ => continue,
+            };
+            let kb: u64 = kb
+                .parse()
+                .map_err(|_| anyhow!("Invalid meminfo line: {:?}", line))?;
+            *dst = Some(kb * 1024);
         }
-        // Since we only report percentage,
-        // we don't care what the units are,
-        // only that they're equal.
-        if total_units == avail_units {
-            Ok(Self {
-                total: total_quant.unwrap_or(0),
-                available: avail_quant.unwrap_or(0),
-            })
-        } else {
-            Err(anyhow!(
-                "Different units in MemTotal:{:?} and MemAvailable:{:?}",
-                total_units,
-                avail_units
-            ))
+        match (total, available) {
+            (Some(total), Some(available)) => Ok(Self {
+                total,
+                available,
+                // Absent when the kernel is built without swap support.
+                swap_total: swap_total.unwrap_or(0),
+                swap_free: swap_free.unwrap_or(0),
+            }),
+            _ => Err(anyhow!("MemTotal or MemAvailable missing in meminfo")),
         }
     }
 
-    fn used(&self) -> u64 {
-        self.total - self.available
+    fn read(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
     }
 
-    fn used_pct(&self) -> Option<u64> {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn used_pct(&self) -> Option<u64> {
         let cur = self.used() as f32;
         let tot = self.total as f32;
         crate::math::percentage_ceiling(cur, tot)
     }
+
+    pub fn swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+
+    /// None when there is no swap.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn swap_used_pct(&self) -> Option<u64> {
+        if self.swap_total == 0 {
+            return None;
+        }
+        let cur = self.swap_used() as f32;
+        let tot = self.swap_total as f32;
+        crate::math::percentage_ceiling(cur, tot)
+    }
 }
 
-fn usage() -> Result<Option<u64>> {
-    Ok(Info::read()?.used_pct())
+/// Percentage of the last 10 seconds in which some task was stalled waiting
+/// on memory: the "some avg10" of PSI.
+pub fn parse_pressure(data: &str) -> Result<f32> {
+    data.lines()
+        .find_map(|line| line.strip_prefix("some "))
+        .and_then(|some| {
+            some.split_whitespace()
+                .find_map(|field| field.strip_prefix("avg10="))
+        })
+        .ok_or_else(|| anyhow!("No \"some avg10\" in pressure: {:?}", data))?
+        .parse()
+        .map_err(|_| {
+            anyhow!("Invalid \"some avg10\" in pressure: {:?}", data)
+        })
+}
+
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// Used percentages which, when reached, trigger an alert.
+    pub alert_triggers: Vec<alert::AlertTrigger>,
+
+    /// Pressure (PSI some avg10) percentages which, when reached, trigger
+    /// an alert.
+    pub alert_pressure: Vec<alert::AlertTrigger>,
+
+    /// Alert when the OOM killer kills a process.
+    pub alert_oom: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Field {
+    /// Percentage of memory used, like "42%".
+    Pct,
+
+    /// Memory used, like "8.9G used".
+    Used,
+
+    /// Memory available, like "12.3G avail".
+    Avail,
+
+    /// Percentage of swap used, like "s 25%".
+    Swap,
+
+    /// Memory pressure (PSI some avg10), like "p 12.3%".
+    Pressure,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reading {
+    info: Option<Info>,
+    pressure: Option<f32>,
+
+    /// OOM kills since boot.
+    oom_kills: Option<u64>,
+
+    /// Names and pids of the processes killed since the previous reading,
+    /// as far as the kernel log tells.
+    killed: Vec<(String, u32)>,
+}
+
+/// Each fires once, when reached, and is re-armed once the value drops
+/// back below it.
+fn triggers(triggers: &[alert::AlertTrigger]) -> alert::Triggers {
+    alert::Triggers::new(triggers, alert::Direction::Rising, Some(0))
 }
 
 struct State<'a> {
     prefix: &'a str,
-    usage: Option<u64>,
+    fields: &'a [Field],
+    reading: Option<Reading>,
+    alerts_used: alert::Triggers,
+    alerts_pressure: alert::Triggers,
+    alert_oom: bool,
 }
 
 impl<'a> State<'a> {
-    fn new(prefix: &'a str) -> Self {
+    fn new(
+        prefix: &'a str,
+        fields: &'a [Field],
+        settings: &Settings,
+    ) -> Self {
         Self {
             prefix,
-            usage: None,
+            fields,
+            reading: None,
+            alerts_used: triggers(&settings.alert_triggers),
+            alerts_pressure: triggers(&settings.alert_pressure),
+            alert_oom: settings.alert_oom,
+        }
+    }
+
+    fn alert_used(&mut self, info: &Info) -> Option<Alert> {
+        let pct = info.used_pct()?;
+        let trigger = self.alerts_used.check(pct)?;
+        let level = trigger.level(alert::Direction::Rising, 80, 95);
+        let summary = format!("Memory usage above {}%!", trigger.threshold);
+        let body = format!(
+            "{}%, with {} available.",
+            pct,
+            crate::units::fmt_bytes(info.available)
+        );
+        Some(Alert::new(level, &summary, &body))
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn alert_pressure(&mut self, pressure: f32) -> Option<Alert> {
+        // Whole percentages, same as the thresholds.
+        let trigger = self.alerts_pressure.check(pressure.floor() as u64)?;
+        let level = trigger.level(alert::Direction::Rising, 10, 50);
+        let summary =
+            format!("Memory pressure above {}%!", trigger.threshold);
+        let body = format!(
+            "Tasks stalled on memory {:.1}% of the last 10 seconds.",
+            pressure
+        );
+        Some(Alert::new(level, &summary, &body))
+    }
+
+    fn alert_oom(
+        &self,
+        prev: Option<u64>,
+        reading: &Reading,
+    ) -> Option<Alert> {
+        if !self.alert_oom {
+            return None;
+        }
+        let kills = reading.oom_kills?.checked_sub(prev?)?;
+        if kills == 0 {
+            return None;
         }
+        let body = if reading.killed.is_empty() {
+            format!("Killed {} process(es).", kills)
+        } else {
+            let names: Vec<String> = reading
+                .killed
+                .iter()
+                .map(|(name, pid)| format!("{} ({})", name, pid))
+                .collect();
+            format!("Killed {}.", names.join(", "))
+        };
+        Some(Alert::new(alert::Level::Hi, "Out of memory!", &body))
+    }
+}
+
+fn write_field<W: std::io::Write>(
+    mut buf: W,
+    field: Field,
+    reading: &Reading,
+) -> Result<()> {
+    use crate::units::fmt_bytes;
+
+    let info = reading.info.as_ref();
+    match field {
+        Field::Pct => match info.and_then(Info::used_pct) {
+            None => write!(buf, "----")?,
+            Some(pct) => write!(buf, "{:3.0}%", pct)?,
+        },
+        Field::Used => match info {
+            None => write!(buf, "----")?,
+            Some(info) => write!(buf, "{} used", fmt_bytes(info.used()))?,
+        },
+        Field::Avail => match info {
+            None => write!(buf, "----")?,
+            Some(info) => {
+                write!(buf, "{} avail", fmt_bytes(info.available))?;
+            }
+        },
+        Field::Swap => match info.and_then(Info::swap_used_pct) {
+            None => write!(buf, "s ----")?,
+            Some(pct) => write!(buf, "s{:3.0}%", pct)?,
+        },
+        Field::Pressure => match reading.pressure {
+            None => write!(buf, "p ----")?,
+            Some(pct) => write!(buf, "p{:5.1}%", pct)?,
+        },
     }
+    Ok(())
 }
 
 impl<'a> crate::pipeline::State for State<'a> {
-    type Event = Option<u64>;
-
-    fn update(
-        &mut self,
-        usage: Self::Event,
-    ) -> Result<Option<Vec<crate::alert::Alert>>> {
-        self.usage = usage;
-        Ok(None)
+    type Event = Reading;
+
+    fn update(&mut self, reading: Self::Event) -> Result<Option<Vec<Alert>>> {
+        let mut alerts = Vec::new();
+        if let Some(info) = &reading.info {
+            alerts.extend(self.alert_used(info));
+        }
+        if let Some(pressure) = reading.pressure {
+            alerts.extend(self.alert_pressure(pressure));
+        }
+        let prev_oom_kills = self.reading.as_ref().and_then(|r| r.oom_kills);
+        alerts.extend(self.alert_oom(prev_oom_kills, &reading));
+        self.reading = Some(reading);
+        Ok((!alerts.is_empty()).then_some(alerts))
     }
 
     fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
         write!(buf, "{}", self.prefix)?;
-        match self.usage {
-            None => write!(buf, "----")?,
-            Some(pct) => write!(buf, "{:3.0}%", pct)?,
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            match &self.reading {
+                None => write!(buf, "----")?,
+                Some(reading) => write_field(&mut buf, *field, reading)?,
+            }
         }
         writeln!(buf)?;
         Ok(())
     }
 }
 
-fn reads(interval: Duration) -> impl Iterator<Item = Option<u64>> {
-    use crate::clock;
-    clock::new(interval).filter_map(|clock::Tick| match usage() {
-        Err(err) => {
+fn read(
+    meminfo: &Path,
+    pressure: Option<&Path>,
+    vmstat: Option<&Path>,
+    kmsg: Option<&mut oom::Kmsg>,
+) -> Reading {
+    let info = Info::read(meminfo)
+        .map_err(|err| {
             tracing::error!("Failed to read memory usage: {:?}", err);
-            None
-        }
-        Ok(usage_opt) => Some(usage_opt),
+        })
+        .ok();
+    let pressure = pressure.and_then(|path| {
+        std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| parse_pressure(&data))
+            .map_err(|err| {
+                tracing::error!("Failed to read memory pressure: {:?}", err);
+            })
+            .ok()
+    });
+    let oom_kills = vmstat.and_then(|path| {
+        oom::read_vmstat(path)
+            .map_err(|err| {
+                tracing::error!("Failed to read OOM kill count: {:?}", err);
+            })
+            .ok()
+    });
+    let killed = kmsg.map(oom::Kmsg::killed).unwrap_or_default();
+    Reading {
+        info,
+        pressure,
+        oom_kills,
+        killed,
+    }
+}
+
+fn reads(
+    interval: Duration,
+    fields: &[Field],
+    settings: &Settings,
+) -> impl Iterator<Item = Reading> {
+    use crate::clock;
+
+    // Not every kernel has PSI, so only bother with it when asked.
+    let pressure = (fields.contains(&Field::Pressure)
+        || !settings.alert_pressure.is_empty())
+    .then_some(Path::new(PROC_PRESSURE_MEMORY));
+    let vmstat = settings.alert_oom.then_some(Path::new(oom::PROC_VMSTAT));
+    // Without it we can still alert, just without naming the killed.
+    let mut kmsg = settings
+        .alert_oom
+        .then(|| {
+            oom::Kmsg::open(Path::new(oom::DEV_KMSG))
+                .map_err(|error| {
+                    tracing::warn!(?error, "Failed to open kernel log.");
+                })
+                .ok()
+        })
+        .flatten();
+    clock::new(interval).map(move |clock::Tick| {
+        read(Path::new(PROC_MEMINFO), pressure, vmstat, kmsg.as_mut())
     })
 }
 
-pub fn run(prefix: &str, interval: Duration) -> Result<()> {
-    crate::pipeline::run_to_stdout(reads(interval), State::new(prefix))
+pub fn run(
+    prefix: &str,
+    interval: Duration,
+    fields: &[Field],
+    settings: &Settings,
+) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(interval, fields, settings),
+        State::new(prefix, fields, settings),
+    )
 }
@@ -0,0 +1,81 @@
+// Noticing the OOM killer at work: the counter in /proc/vmstat says that it
+// happened, while only the kernel log says to whom.
+
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+
+pub const PROC_VMSTAT: &str = "/proc/vmstat";
+pub const DEV_KMSG: &str = "/dev/kmsg";
+
+/// Total number of processes killed by the OOM killer since boot.
+pub fn parse_vmstat(data: &str) -> Result<u64> {
+    data.lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .ok_or_else(|| anyhow!("No oom_kill in vmstat"))?
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid oom_kill in vmstat"))
+}
+
+pub fn read_vmstat(path: &Path) -> Result<u64> {
+    parse_vmstat(&std::fs::read_to_string(path)?)
+}
+
+/// Process name and pid, from a kernel log record like:
+/// "3,1234,5678901,-;Out of memory: Killed process 4321 (firefox) ...".
+pub fn parse_killed(record: &str) -> Option<(String, u32)> {
+    let (_, msg) = record.split_once(';')?;
+    let (_, rest) = msg.split_once("Killed process ")?;
+    let (pid, rest) = rest.split_once(" (")?;
+    let (name, _) = rest.split_once(')')?;
+    Some((name.to_string(), pid.parse().ok()?))
+}
+
+/// Kernel log, read as it is written, from when we started.
+pub struct Kmsg {
+    file: File,
+}
+
+impl Kmsg {
+    /// Needs permission to read the kernel log, which is not given by
+    /// default on some distributions (kernel.dmesg_restrict=1).
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?;
+        // Only what is new.
+        file.seek(std::io::SeekFrom::End(0))?;
+        Ok(Self { file })
+    }
+
+    /// Processes killed since the last time, as named in the log.
+    pub fn killed(&mut self) -> Vec<(String, u32)> {
+        let mut killed = Vec::new();
+        // Each read is exactly one record.
+        let mut buf = vec![0; 8192];
+        loop {
+            match self.file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let record = String::from_utf8_lossy(&buf[..n]);
+                    killed.extend(parse_killed(&record));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // Records were overwritten before we got to them.
+                Err(e) if e.raw_os_error() == Some(libc::EPIPE) => {}
+                Err(error) => {
+                    tracing::error!(?error, "Failed to read kernel log.");
+                    break;
+                }
+            }
+        }
+        killed
+    }
+}
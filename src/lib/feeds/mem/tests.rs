@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use crate::alert::AlertTrigger;
+
+use super::{oom, Field, Info, Reading, Settings};
+
+#[test]
+fn parse() {
+    let data = std::fs::read_to_string("tests/proc-meminfo.txt").unwrap();
+    let info = Info::parse(&data).unwrap();
+    assert_eq!(
+        Info {
+            total: 32_622_480 * 1024,
+            available: 20_514_816 * 1024,
+            swap_total: 8_388_604 * 1024,
+            swap_free: 6_291_452 * 1024,
+        },
+        info
+    );
+    assert_eq!(Some(38), info.used_pct());
+    assert_eq!(Some(26), info.swap_used_pct());
+
+    let no_swap =
+        Info::parse("MemTotal: 100 kB\nMemAvailable: 50 kB").unwrap();
+    assert_eq!(None, no_swap.swap_used_pct());
+    assert!(Info::parse("MemTotal: 100 kB").is_err());
+    assert!(Info::parse("MemTotal: 100 kB\nMemAvailable: x kB").is_err());
+}
+
+#[test]
+fn pressure() {
+    let data =
+        std::fs::read_to_string("tests/proc-pressure-memory.txt").unwrap();
+    assert_eq!(12.34, super::parse_pressure(&data).unwrap());
+    assert!(super::parse_pressure("full avg10=1.00").is_err());
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let fields = [
+        Field::Pct,
+        Field::Used,
+        Field::Avail,
+        Field::Swap,
+        Field::Pressure,
+    ];
+    let mut state = super::State::new("m ", &fields, &Settings::default());
+    let mut buf = Vec::new();
+    state.display(&mut buf).unwrap();
+    let reading = super::read(
+        Path::new("tests/proc-meminfo.txt"),
+        Some(Path::new("tests/proc-pressure-memory.txt")),
+        None,
+        None,
+    );
+    state.update(reading).unwrap();
+    state.display(&mut buf).unwrap();
+    let reading =
+        super::read(Path::new("tests/proc-meminfo.txt"), None, None, None);
+    state.update(reading).unwrap();
+    state.display(&mut buf).unwrap();
+    assert_eq!(
+        "m ---- ---- ---- ---- ----\n\
+         m  38% 11.5G used 19.6G avail s 26% p 12.3%\n\
+         m  38% 11.5G used 19.6G avail s 26% p ----\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn oom_parse() {
+    assert_eq!(
+        3,
+        oom::parse_vmstat("pgfault 123\noom_kill 3\npgmajfault 4").unwrap()
+    );
+    assert!(oom::parse_vmstat("pgfault 123").is_err());
+    assert_eq!(
+        Some(("Web Content".to_string(), 4321)),
+        oom::parse_killed(
+            "3,1234,5678901,-;Out of memory: Killed process 4321 \
+             (Web Content) total-vm:2841744kB, anon-rss:1562484kB, \
+             file-rss:0kB, shmem-rss:0kB, UID:1000 pgtables:4528kB \
+             oom_score_adj:100"
+        )
+    );
+    assert_eq!(
+        None,
+        oom::parse_killed(
+            "6,1235,5678902,-;oom-kill:constraint=CONSTRAINT_NONE,\
+             task=firefox,pid=4321,uid=1000"
+        )
+    );
+}
+
+#[test]
+fn alerts() {
+    use crate::pipeline::State;
+
+    const G: u64 = 1024 * 1024 * 1024;
+    let reading = |used: u64, pressure: f32| Reading {
+        info: Some(Info {
+            total: 100 * G,
+            available: (100 - used) * G,
+            swap_total: 0,
+            swap_free: 0,
+        }),
+        pressure: Some(pressure),
+        oom_kills: None,
+        killed: Vec::new(),
+    };
+    let settings = Settings {
+        alert_triggers: vec![
+            AlertTrigger::from(80),
+            "95:hi".parse().unwrap(),
+        ],
+        alert_pressure: vec![AlertTrigger::from(10)],
+        alert_oom: true,
+    };
+    let mut state = super::State::new("m ", &[Field::Pct], &settings);
+    let counts: Vec<usize> = [
+        (50, 0.0),
+        (80, 0.0),  // Reached.
+        (85, 9.9),  // Already sent.
+        (97, 12.5), // Both.
+        (96, 20.0),
+        (79, 5.0),  // Both re-armed, all of 80 and 95.
+        (96, 10.0), // Both, but just the highest of used.
+    ]
+    .iter()
+    .map(|(used, pressure)| {
+        let alerts = state.update(reading(*used, *pressure)).unwrap();
+        alerts.map_or(0, |a| a.len())
+    })
+    .collect();
+    assert_eq!(vec![0, 1, 0, 2, 0, 0, 2], counts);
+
+    let oom = |kills: Option<u64>, killed: &[(&str, u32)]| Reading {
+        oom_kills: kills,
+        killed: killed
+            .iter()
+            .map(|(name, pid)| (name.to_string(), *pid))
+            .collect(),
+        ..Reading::default()
+    };
+    let counts: Vec<usize> = [
+        oom(Some(2), &[]), // Only the baseline.
+        oom(Some(2), &[]),
+        oom(Some(3), &[("firefox", 4321)]),
+        oom(Some(4), &[]), // Not in the log, but still killed.
+        oom(None, &[]),
+        oom(Some(5), &[]), // Unknown since when.
+    ]
+    .into_iter()
+    .map(|reading| state.update(reading).unwrap().map_or(0, |a| a.len()))
+    .collect();
+    assert_eq!(vec![0, 0, 1, 1, 0, 0], counts);
+}
@@ -1,10 +1,16 @@
 pub mod backlight;
 pub mod bluetooth;
+pub mod cpu;
+pub mod cpufreq;
 pub mod disk;
+pub mod diskio;
+pub mod leds;
+pub mod load;
 pub mod mem;
 pub mod mpd;
 pub mod net;
 pub mod pulseaudio;
+pub mod temp;
 pub mod upower;
 pub mod weather;
 pub mod x11;
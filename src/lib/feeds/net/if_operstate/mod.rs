@@ -5,6 +5,8 @@ use std::{
 
 use anyhow::Result;
 
+use super::netlink::{self, Updates};
+
 #[derive(Debug)]
 enum Status {
     Up,
@@ -83,6 +85,7 @@ impl<'a> crate::pipeline::State for State<'a> {
 fn reads(
     interval: Duration,
     interface: &str,
+    updates: Updates,
 ) -> impl Iterator<Item = Option<Status>> {
     use crate::clock;
 
@@ -90,20 +93,26 @@ fn reads(
         ["/sys/class/net", interface, "operstate"].iter().collect();
     tracing::info!("operstate path: {:?}", &path);
 
-    clock::new(interval).filter_map(move |clock::Tick| {
-        match Status::read(&path) {
+    // Operstate changes only along with the link, so no need to also poll.
+    netlink::ticks(interface, interval, updates, false).filter_map(
+        move |clock::Tick| match Status::read(&path) {
             Err(err) => {
                 tracing::error!("Failed to read operstate: {:?}", err);
                 None
             }
             Ok(status_opt) => Some(status_opt),
-        }
-    })
+        },
+    )
 }
 
-pub fn run(interval: Duration, interface: &str, prefix: &str) -> Result<()> {
+pub fn run(
+    interval: Duration,
+    interface: &str,
+    prefix: &str,
+    updates: Updates,
+) -> Result<()> {
     crate::pipeline::run_to_stdout(
-        reads(interval, interface),
+        reads(interval, interface, updates),
         State::new(prefix),
     )
 }
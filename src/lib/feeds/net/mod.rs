@@ -23,4 +23,6 @@
 //      > in terms of kernel memory usage.
 
 pub mod if_operstate;
+pub mod netlink;
+pub mod traffic;
 pub mod wifi_link_qual;
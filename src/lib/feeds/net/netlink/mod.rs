@@ -0,0 +1,261 @@
+// Link and address change notifications, from rtnetlink, so that the net
+// feeds can update right away, rather than on the next poll, and not wake
+// up at all while nothing changes. Polling remains as a fallback, for when
+// netlink is unavailable, like in some sandboxes and containers.
+#[cfg(test)]
+mod tests;
+
+use std::{
+    ffi::CString,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::clock::{self, Tick};
+
+/// Of nlmsghdr, all of which precedes the payload.
+const HEADER_LEN: usize = 16;
+
+/// Of the payload, in both ifinfomsg and ifaddrmsg.
+const INDEX_OFFSET: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Updates {
+    /// On link and address changes, as notified by netlink, falling back to
+    /// polling when that is unavailable.
+    Notify,
+
+    /// On the interval only.
+    Poll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Link,
+    Addr,
+}
+
+/// Interface, by index, whose link or address changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    pub kind: Kind,
+    pub index: u32,
+}
+
+/// Changes in a datagram of rtnetlink messages. Those of other types are
+/// skipped, as is anything after a truncated message.
+pub fn parse(buf: &[u8]) -> Vec<Change> {
+    let u16_at = |i: usize| buf.get(i..i + 2).map(|b| [b[0], b[1]]);
+    let u32_at = |i: usize| {
+        buf.get(i..i + 4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut changes = Vec::new();
+    let mut offset = 0;
+    while let (Some(len), Some(typ)) = (u32_at(offset), u16_at(offset + 4)) {
+        let len = len as usize;
+        if len < HEADER_LEN || offset + len > buf.len() {
+            tracing::warn!(offset, len, "Truncated netlink message.");
+            break;
+        }
+        let kind = match u16::from_ne_bytes(typ) {
+            libc::RTM_NEWLINK | libc::RTM_DELLINK => Some(Kind::Link),
+            libc::RTM_NEWADDR | libc::RTM_DELADDR => Some(Kind::Addr),
+            _ => None,
+        };
+        if let (Some(kind), Some(index)) =
+            (kind, u32_at(offset + HEADER_LEN + INDEX_OFFSET))
+        {
+            changes.push(Change { kind, index });
+        }
+        // Messages are aligned to 4 bytes.
+        offset += (len + 3) & !3;
+    }
+    changes
+}
+
+/// Subscribed to link and address notifications of all interfaces.
+fn socket() -> Result<OwnedFd> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(anyhow!(
+            "Failed to open netlink socket: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = (libc::RTMGRP_LINK
+        | libc::RTMGRP_IPV4_IFADDR
+        | libc::RTMGRP_IPV6_IFADDR) as u32;
+    if unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            std::ptr::addr_of!(addr).cast(),
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    } < 0
+    {
+        return Err(anyhow!(
+            "Failed to bind netlink socket: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(fd)
+}
+
+#[derive(Debug)]
+enum Event {
+    Poll,
+
+    /// None when some notifications were lost, to an overrun of the socket
+    /// buffer, so any of them could have been ours.
+    Changes(Option<Vec<Change>>),
+}
+
+/// Blocks on the socket until it fails, then polls instead.
+fn receive(fd: &OwnedFd, sender: &Sender<Event>) -> Result<()> {
+    let mut buf = vec![0u8; 8192];
+    loop {
+        let n = unsafe {
+            libc::recv(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0)
+        };
+        let event = match n {
+            n if n >= 0 => Event::Changes(Some(parse(&buf[..n as usize]))),
+            _ => match std::io::Error::last_os_error() {
+                e if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    tracing::warn!("Netlink notifications lost.");
+                    Event::Changes(None)
+                }
+                e if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                e => return Err(e.into()),
+            },
+        };
+        if sender.send(event).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+fn poll(interval: Duration, sender: &Sender<Event>) {
+    for Tick in clock::new(interval).skip(1) {
+        if sender.send(Event::Poll).is_err() {
+            break;
+        }
+    }
+}
+
+/// Index of the interface, or 0 when it does not exist (at the moment).
+fn index(interface: &str) -> u32 {
+    CString::new(interface)
+        .map(|name| unsafe { libc::if_nametoindex(name.as_ptr()) })
+        .unwrap_or(0)
+}
+
+pub struct Watcher {
+    interface: String,
+    receiver: Receiver<Event>,
+    first: bool,
+
+    /// Last known, so that a change is still recognized as ours after the
+    /// interface is gone, and with it its index.
+    index: u32,
+}
+
+impl Watcher {
+    /// First tick is immediate, subsequent ones after the link or the
+    /// addresses of the interface change, and also after each interval if
+    /// keep_polling. Ticks only after each interval if netlink is
+    /// unavailable, or becomes so.
+    pub fn new(
+        interface: &str,
+        interval: Duration,
+        keep_polling: bool,
+    ) -> Result<Self> {
+        let fd = socket()?;
+        let (sender, receiver) = mpsc::channel();
+        if keep_polling {
+            let sender = sender.clone();
+            std::thread::spawn(move || poll(interval, &sender));
+        }
+        std::thread::spawn(move || {
+            if let Err(error) = receive(&fd, &sender) {
+                tracing::error!(?error, "Netlink failed. Polling instead.");
+                if !keep_polling {
+                    poll(interval, &sender);
+                }
+            }
+        });
+        Ok(Self {
+            interface: interface.to_string(),
+            receiver,
+            first: true,
+            index: index(interface),
+        })
+    }
+
+    fn is_ours(&mut self, changes: &[Change]) -> bool {
+        let prev = self.index;
+        self.index = match index(&self.interface) {
+            0 => prev,
+            index => index,
+        };
+        changes
+            .iter()
+            .any(|c| c.index == self.index || c.index == prev)
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first {
+            self.first = false;
+            return Some(Tick);
+        }
+        loop {
+            match self.receiver.recv().ok()? {
+                Event::Poll | Event::Changes(None) => return Some(Tick),
+                Event::Changes(Some(changes)) => {
+                    if self.is_ours(&changes) {
+                        tracing::debug!(?changes, "Change.");
+                        return Some(Tick);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Ticks as chosen, with the caveats of Watcher::new, which is what
+/// keep_polling is for.
+pub fn ticks(
+    interface: &str,
+    interval: Duration,
+    updates: Updates,
+    keep_polling: bool,
+) -> Box<dyn Iterator<Item = Tick>> {
+    match updates {
+        Updates::Poll => Box::new(clock::new(interval)),
+        Updates::Notify => {
+            match Watcher::new(interface, interval, keep_polling) {
+                Ok(watcher) => Box::new(watcher),
+                Err(error) => {
+                    tracing::warn!(?error, "Netlink unavailable. Polling.");
+                    Box::new(clock::new(interval))
+                }
+            }
+        }
+    }
+}
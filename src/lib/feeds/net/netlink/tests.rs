@@ -0,0 +1,53 @@
+use super::{Change, Kind};
+
+/// nlmsghdr, followed by a payload starting with a family byte and,
+/// at offset 4, the interface index.
+fn message(typ: u16, index: u32, payload_len: usize) -> Vec<u8> {
+    let len = u32::try_from(16 + payload_len).unwrap();
+    let mut msg = Vec::new();
+    msg.extend(len.to_ne_bytes());
+    msg.extend(typ.to_ne_bytes());
+    msg.extend(0u16.to_ne_bytes()); // Flags.
+    msg.extend(0u32.to_ne_bytes()); // Sequence number.
+    msg.extend(0u32.to_ne_bytes()); // Port id.
+    let mut payload = vec![0u8; payload_len];
+    payload[0] = libc::AF_UNSPEC as u8;
+    payload[4..8].copy_from_slice(&index.to_ne_bytes());
+    msg.extend(payload);
+    // Padded to the next message.
+    msg.resize((msg.len() + 3) & !3, 0);
+    msg
+}
+
+#[test]
+fn parse() {
+    let mut buf = Vec::new();
+    buf.extend(message(libc::RTM_NEWLINK, 3, 33));
+    buf.extend(message(libc::RTM_NEWROUTE, 9, 12));
+    buf.extend(message(libc::RTM_DELADDR, 2, 8));
+    assert_eq!(
+        vec![
+            Change {
+                kind: Kind::Link,
+                index: 3
+            },
+            Change {
+                kind: Kind::Addr,
+                index: 2
+            }
+        ],
+        super::parse(&buf)
+    );
+    assert!(super::parse(&[]).is_empty());
+
+    // Stops at the truncated one.
+    let mut buf = message(libc::RTM_DELLINK, 4, 16);
+    buf.extend(&message(libc::RTM_NEWADDR, 5, 8)[..20]);
+    assert_eq!(
+        vec![Change {
+            kind: Kind::Link,
+            index: 4
+        }],
+        super::parse(&buf)
+    );
+}
@@ -0,0 +1,332 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+pub const PROC_NET_DEV: &str = "/proc/net/dev";
+
+/// Bytes since the interface came up.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Counters {
+    pub rx: u64,
+    pub tx: u64,
+}
+
+/// Interface names with their counters, in the order listed.
+pub fn parse(data: &str) -> Result<Vec<(String, Counters)>> {
+    data.lines()
+        // Past the two header lines, which have a "|" instead of a ":".
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, fields)| {
+            let fields: Vec<&str> = fields.split_whitespace().collect();
+            let num = |i: usize| -> Result<u64> {
+                fields.get(i).and_then(|n| n.parse().ok()).ok_or_else(|| {
+                    anyhow!("Invalid net/dev line for: {:?}", name.trim())
+                })
+            };
+            let counters = Counters {
+                rx: num(0)?,
+                tx: num(8)?,
+            };
+            Ok((name.trim().to_string(), counters))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Interface(String),
+
+    /// All, except loopback, combined.
+    All,
+}
+
+impl std::str::FromStr for Selection {
+    type Err = anyhow::Error;
+
+    /// Interface name, or "all".
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "" => Err(anyhow!("Empty interface name")),
+            "all" => Ok(Self::All),
+            _ => Ok(Self::Interface(s.to_string())),
+        }
+    }
+}
+
+impl Selection {
+    /// Of each selected interface, by name, so that those coming and going
+    /// can be told apart from traffic. None when the interface is not there.
+    fn counters(
+        &self,
+        all: &[(String, Counters)],
+    ) -> Option<BTreeMap<String, Counters>> {
+        let selected: BTreeMap<String, Counters> = all
+            .iter()
+            .filter(|(name, _)| match self {
+                Self::Interface(selected) => name == selected,
+                Self::All => name != "lo",
+            })
+            .cloned()
+            .collect();
+        match self {
+            Self::Interface(_) if selected.is_empty() => None,
+            _ => Some(selected),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Self::Interface(name) => name,
+            Self::All => "all",
+        }
+    }
+}
+
+/// What the cumulative totals, in the summary file, are counted from.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Totals {
+    /// Since boot, or rather since the interface came up.
+    Boot,
+
+    /// Since the feed started.
+    Start,
+}
+
+impl Counters {
+    fn sum<'a>(counters: impl Iterator<Item = &'a Self>) -> Self {
+        counters.fold(Self::default(), |sum, c| Self {
+            rx: sum.rx + c.rx,
+            tx: sum.tx + c.tx,
+        })
+    }
+
+    /// Summed over the interfaces in both, so that one coming or going is
+    /// not mistaken for traffic. Counters restart when an interface is
+    /// re-created.
+    fn delta(
+        prev: &BTreeMap<String, Self>,
+        cur: &BTreeMap<String, Self>,
+    ) -> Self {
+        let deltas: Vec<Self> = cur
+            .iter()
+            .filter_map(|(name, cur)| {
+                prev.get(name).map(|prev| Self {
+                    rx: cur.rx.saturating_sub(prev.rx),
+                    tx: cur.tx.saturating_sub(prev.tx),
+                })
+            })
+            .collect();
+        Self::sum(deltas.iter())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    time: Instant,
+    counters: Option<BTreeMap<String, Counters>>,
+}
+
+/// Bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rates {
+    rx: u64,
+    tx: u64,
+}
+
+impl Rates {
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation
+    )]
+    fn between(prev: &Sample, cur: &Sample) -> Option<Self> {
+        let delta =
+            Counters::delta(prev.counters.as_ref()?, cur.counters.as_ref()?);
+        let secs = cur.time.duration_since(prev.time).as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+        let rate = |bytes: u64| (bytes as f64 / secs).round() as u64;
+        Some(Self {
+            rx: rate(delta.rx),
+            tx: rate(delta.tx),
+        })
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Summary<'a> {
+    interface: &'a str,
+    rx_bytes_per_sec: Option<u64>,
+    tx_bytes_per_sec: Option<u64>,
+    totals_since: Option<&'static str>,
+    rx_total_bytes: Option<u64>,
+    tx_total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub prefix: String,
+
+    /// Also write the rates and, if chosen, the totals, as JSON, to this
+    /// file on each update.
+    pub summary_file: Option<PathBuf>,
+    pub totals: Option<Totals>,
+}
+
+struct State<'a> {
+    settings: &'a Settings,
+    selection: &'a Selection,
+    prev: Option<Sample>,
+    rates: Option<Rates>,
+
+    /// Since start, accumulated per update, so that the interfaces which
+    /// are gone keep what they counted.
+    since_start: Option<Counters>,
+}
+
+impl<'a> State<'a> {
+    fn new(settings: &'a Settings, selection: &'a Selection) -> Self {
+        Self {
+            settings,
+            selection,
+            prev: None,
+            rates: None,
+            since_start: None,
+        }
+    }
+
+    fn totals(&self) -> Option<Counters> {
+        match self.settings.totals? {
+            Totals::Boot => Some(Counters::sum(
+                self.prev.as_ref()?.counters.as_ref()?.values(),
+            )),
+            Totals::Start => self.since_start,
+        }
+    }
+
+    /// Failure is only logged, since the status line itself is fine.
+    fn write_summary(&self) {
+        let Some(path) = &self.settings.summary_file else {
+            return;
+        };
+        if let Err(error) = serde_json::to_string(&self.summary())
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(path, json)?))
+        {
+            tracing::error!(?path, ?error, "Failed to write summary.");
+        }
+    }
+
+    fn summary(&self) -> Summary<'_> {
+        let totals = self.totals();
+        Summary {
+            interface: self.selection.name(),
+            rx_bytes_per_sec: self.rates.map(|r| r.rx),
+            tx_bytes_per_sec: self.rates.map(|r| r.tx),
+            totals_since: self.settings.totals.map(|t| match t {
+                Totals::Boot => "boot",
+                Totals::Start => "start",
+            }),
+            rx_total_bytes: totals.map(|t| t.rx),
+            tx_total_bytes: totals.map(|t| t.tx),
+        }
+    }
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Sample;
+
+    fn update(
+        &mut self,
+        sample: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        self.rates = self
+            .prev
+            .as_ref()
+            .and_then(|prev| Rates::between(prev, &sample));
+        // Totals since start count from when the interface was first seen.
+        if let Some(cur) = &sample.counters {
+            let delta = self
+                .prev
+                .as_ref()
+                .and_then(|prev| prev.counters.as_ref())
+                .map(|prev| Counters::delta(prev, cur))
+                .unwrap_or_default();
+            let total = self.since_start.unwrap_or_default();
+            self.since_start = Some(Counters {
+                rx: total.rx + delta.rx,
+                tx: total.tx + delta.tx,
+            });
+        }
+        self.prev = Some(sample);
+        self.write_summary();
+        Ok(None)
+    }
+
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        use crate::units::fmt_bytes;
+
+        write!(buf, "{}", self.settings.prefix)?;
+        // Fixed width, so that the rest of the bar does not jump around:
+        // fmt_bytes is at most 7 characters long, like "1023.9K".
+        match self.rates {
+            None => write!(buf, "rx {:>9} tx {:>9}", "----", "----")?,
+            Some(Rates { rx, tx }) => write!(
+                buf,
+                "rx {:>7}/s tx {:>7}/s",
+                fmt_bytes(rx),
+                fmt_bytes(tx)
+            )?,
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+fn read(path: &Path, selection: &Selection) -> Result<Sample> {
+    let all = parse(&std::fs::read_to_string(path)?)?;
+    let counters = selection.counters(&all);
+    if counters.is_none() {
+        tracing::warn!(?selection, "Interface not found.");
+    }
+    Ok(Sample {
+        time: Instant::now(),
+        counters,
+    })
+}
+
+fn reads(
+    interval: Duration,
+    selection: &Selection,
+) -> impl Iterator<Item = Sample> + '_ {
+    use crate::clock;
+
+    clock::new(interval).filter_map(|clock::Tick| {
+        match read(Path::new(PROC_NET_DEV), selection) {
+            Err(err) => {
+                tracing::error!("Failed to read net/dev: {:?}", err);
+                None
+            }
+            Ok(sample) => Some(sample),
+        }
+    })
+}
+
+pub fn run(
+    interval: Duration,
+    selection: &Selection,
+    settings: &Settings,
+) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(interval, selection),
+        State::new(settings, selection),
+    )
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::test_util::TempDir;
+
+use super::{Counters, Sample, Selection, Settings, Totals};
+
+#[test]
+fn parse() {
+    let data = std::fs::read_to_string("tests/proc-net-dev.txt").unwrap();
+    let all = super::parse(&data).unwrap();
+    let names: Vec<&str> =
+        all.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(vec!["lo", "enp0s31f6", "wlp2s0", "wg0"], names);
+    assert_eq!(
+        Counters {
+            rx: 102_400_000,
+            tx: 20_480_000
+        },
+        all[3].1
+    );
+    assert!(super::parse("eth0: 1 2 3").is_err());
+
+    let wlp2s0 = Counters {
+        rx: 8_274_629_837,
+        tx: 627_130_912,
+    };
+    assert_eq!(
+        Some(BTreeMap::from([("wlp2s0".to_string(), wlp2s0)])),
+        "wlp2s0".parse::<Selection>().unwrap().counters(&all)
+    );
+    let selected =
+        "all".parse::<Selection>().unwrap().counters(&all).unwrap();
+    assert_eq!(
+        vec!["enp0s31f6", "wg0", "wlp2s0"],
+        selected.keys().collect::<Vec<&String>>()
+    );
+    assert_eq!(wlp2s0, selected["wlp2s0"]);
+    assert_eq!(None, "eth0".parse::<Selection>().unwrap().counters(&all));
+    assert!("".parse::<Selection>().is_err());
+}
+
+#[test]
+fn display_and_summary() {
+    use crate::pipeline::State;
+
+    let dir = TempDir::new("net-traffic-summary");
+    let summary_file = dir.join("summary.json");
+    let settings = Settings {
+        prefix: "net ".to_string(),
+        summary_file: Some(summary_file.clone()),
+        totals: Some(Totals::Start),
+    };
+    let selection = Selection::All;
+    let mut state = super::State::new(&settings, &selection);
+    let start = Instant::now();
+    let sample = |secs: u64, counters: &[(&str, u64, u64)]| Sample {
+        time: start + Duration::from_secs(secs),
+        counters: (!counters.is_empty()).then(|| {
+            counters
+                .iter()
+                .map(|&(name, rx, tx)| {
+                    (name.to_string(), Counters { rx, tx })
+                })
+                .collect()
+        }),
+    };
+    let mut buf = Vec::new();
+    for s in [
+        sample(0, &[]),
+        sample(5, &[("eth0", 10_000_000, 500), ("wg0", 1_000, 1_000)]),
+        sample(10, &[("eth0", 15_242_880, 5620), ("wg0", 1_000, 1_000)]),
+        // Gone, along with its counters, which are not negative traffic.
+        sample(15, &[("eth0", 15_242_880, 5620)]),
+        // Re-created, counting from zero again.
+        sample(20, &[("eth0", 15_242_880, 5620), ("wg0", 0, 0)]),
+        sample(25, &[("eth0", 15_242_880, 5620), ("wg0", 5_000, 0)]),
+    ] {
+        state.update(s).unwrap();
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(
+        "net rx      ---- tx      ----\n\
+         net rx      ---- tx      ----\n\
+         net rx    1.0M/s tx    1.0K/s\n\
+         net rx      0B/s tx      0B/s\n\
+         net rx      0B/s tx      0B/s\n\
+         net rx   1000B/s tx      0B/s\n",
+        String::from_utf8(buf).unwrap()
+    );
+
+    let summary: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&summary_file).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(Some("all"), summary["interface"].as_str());
+    assert_eq!(Some(1000), summary["rx_bytes_per_sec"].as_u64());
+    assert_eq!(Some("start"), summary["totals_since"].as_str());
+    assert_eq!(Some(5_247_880), summary["rx_total_bytes"].as_u64());
+    assert_eq!(Some(5120), summary["tx_total_bytes"].as_u64());
+}
@@ -2,6 +2,8 @@ use anyhow::{anyhow, Error, Result};
 
 use std::{io::BufRead, time::Duration}; // .lines()
 
+use super::netlink::{self, Updates};
+
 const PROC_NET_WIRELESS: &str = "/proc/net/wireless";
 
 struct State<'a> {
@@ -88,21 +90,31 @@ fn parse(
 fn reads(
     interval: Duration,
     interface: &str,
+    updates: Updates,
 ) -> impl Iterator<Item = Option<u64>> + '_ {
     use crate::clock;
 
-    clock::new(interval).filter_map(|clock::Tick| match read(interface) {
-        Ok(pct_opt) => Some(pct_opt),
-        Err(err) => {
-            tracing::error!("Failed to read link quality: {:?}", err);
-            None
-        }
-    })
+    // Link quality changes without notifications, so those only make
+    // (dis)associations show up sooner, while polling continues.
+    netlink::ticks(interface, interval, updates, true).filter_map(
+        |clock::Tick| match read(interface) {
+            Ok(pct_opt) => Some(pct_opt),
+            Err(err) => {
+                tracing::error!("Failed to read link quality: {:?}", err);
+                None
+            }
+        },
+    )
 }
 
-pub fn run(interval: Duration, interface: &str, prefix: &str) -> Result<()> {
+pub fn run(
+    interval: Duration,
+    interface: &str,
+    prefix: &str,
+    updates: Updates,
+) -> Result<()> {
     crate::pipeline::run_to_stdout(
-        reads(interval, interface),
+        reads(interval, interface, updates),
         State::new(prefix),
     )
 }
@@ -0,0 +1,105 @@
+// Alerting on sensors running hot, each on its own, since a hot GPU does
+// not make a cool NVMe drive any hotter.
+
+use std::collections::HashMap;
+
+use crate::alert::{self, Alert};
+
+use super::{Id, Temp, Unit};
+
+/// Degrees Celsius to cool down by, below a limit, before it can alert
+/// again, so that hovering around it does not spam.
+const HYSTERESIS: f32 = 5.0;
+
+/// Temperature which, when reached, triggers an alert. In the displayed
+/// unit.
+pub type AlertTrigger = alert::AlertTrigger<f32>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub triggers: Vec<AlertTrigger>,
+
+    /// Alert when a sensor reaches its own max or crit limit.
+    pub sensor_limits: bool,
+}
+
+#[derive(Debug)]
+pub struct Alerts {
+    /// In degrees Celsius.
+    triggers: Vec<AlertTrigger>,
+    sensor_limits: bool,
+    unit: Unit,
+
+    /// Of the triggers and of the sensor's own limits, by sensor.
+    sensors: HashMap<Id, alert::Triggers<f32>>,
+}
+
+impl Alerts {
+    pub fn new(settings: &Settings, unit: Unit) -> Self {
+        Self {
+            triggers: settings
+                .triggers
+                .iter()
+                .map(|t| AlertTrigger {
+                    threshold: unit.to_celsius(t.threshold),
+                    level: t.level,
+                })
+                .collect(),
+            sensor_limits: settings.sensor_limits,
+            unit,
+            sensors: HashMap::new(),
+        }
+    }
+
+    fn triggers(&self, temp: &Temp) -> alert::Triggers<f32> {
+        let mut triggers = self.triggers.clone();
+        if self.sensor_limits {
+            let limit = |threshold, level| AlertTrigger {
+                threshold,
+                level: Some(level),
+            };
+            triggers.extend(temp.max.map(|c| limit(c, alert::Level::Mid)));
+            triggers.extend(temp.crit.map(|c| limit(c, alert::Level::Hi)));
+        }
+        alert::Triggers::new(
+            &triggers,
+            alert::Direction::Rising,
+            Some(HYSTERESIS),
+        )
+    }
+
+    pub fn update(&mut self, temps: &[Temp]) -> Vec<Alert> {
+        let unit = self.unit;
+        let mut alerts = Vec::new();
+        for temp in temps {
+            if !self.sensors.contains_key(&temp.id) {
+                let triggers = self.triggers(temp);
+                self.sensors.insert(temp.id.clone(), triggers);
+            }
+            let Some(trigger) = self
+                .sensors
+                .get_mut(&temp.id)
+                .and_then(|triggers| triggers.check(temp.celsius))
+            else {
+                continue;
+            };
+            let summary = format!(
+                "{} above {:.0}{}!",
+                temp.id,
+                unit.from_celsius(trigger.threshold),
+                unit.symbol()
+            );
+            let body = format!(
+                "{:.0}{}",
+                unit.from_celsius(temp.celsius),
+                unit.symbol()
+            );
+            alerts.push(Alert::new(
+                trigger.level.unwrap_or(alert::Level::Mid),
+                &summary,
+                &body,
+            ));
+        }
+        alerts
+    }
+}
@@ -0,0 +1,373 @@
+pub mod alerts;
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+pub const HWMON: &str = "/sys/class/hwmon";
+pub const THERMAL: &str = "/sys/class/thermal";
+
+/// Sensor identity which, unlike the hwmon index, is stable across boots:
+/// the chip (driver) name and the label of the input, like
+/// "coretemp/Package id 0". Inputs without a label are named after their
+/// attribute, like "thinkpad/temp1", while thermal zones are named by their
+/// type, like "x86_pkg_temp".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Id {
+    pub chip: String,
+    pub label: Option<String>,
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.label {
+            None => write!(f, "{}", self.chip),
+            Some(label) => write!(f, "{}/{}", self.chip, label),
+        }
+    }
+}
+
+/// In degrees Celsius.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Temp {
+    pub id: Id,
+    pub celsius: f32,
+
+    /// The sensor's own limits, where it has any.
+    pub max: Option<f32>,
+    pub crit: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fan {
+    pub id: Id,
+    pub rpm: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reading {
+    pub temps: Vec<Temp>,
+    pub fans: Vec<Fan>,
+}
+
+fn read_trimmed(path: &Path) -> Result<String> {
+    Ok(std::fs::read_to_string(path)?.trim().to_string())
+}
+
+/// Millidegrees, as all of sysfs has them.
+#[allow(clippy::cast_precision_loss)]
+fn read_millidegrees(path: &Path) -> Result<f32> {
+    let millis: i64 = read_trimmed(path)?
+        .parse()
+        .map_err(|_| anyhow!("Invalid temperature in: {:?}", path))?;
+    Ok(millis as f32 / 1000.0)
+}
+
+/// Sorted entries, with missing directory the same as empty.
+fn list(dir: &Path, prefix: &str) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut paths = Vec::new();
+    for entry_result in entries {
+        let entry = entry_result?;
+        if entry.file_name().to_string_lossy().starts_with(prefix) {
+            paths.push(entry.path());
+        }
+    }
+    // Numerically, so that temp10 comes after temp9.
+    paths.sort_by_key(|path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let digits: String =
+            name.chars().filter(char::is_ascii_digit).collect();
+        (digits.len(), name.to_string())
+    });
+    Ok(paths)
+}
+
+/// Numbers of the inputs of this kind, like 1 and 2 of temp1_input and
+/// temp2_input.
+fn inputs(dir: &Path, kind: &str) -> Result<Vec<String>> {
+    Ok(list(dir, kind)?
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let n = name.strip_prefix(kind)?.strip_suffix("_input")?;
+            Some(n.to_string())
+        })
+        .collect())
+}
+
+fn read_hwmon(dir: &Path, reading: &mut Reading) -> Result<()> {
+    let chip = read_trimmed(&dir.join("name"))?;
+    let id = |kind: &str, n: &str| Id {
+        chip: chip.clone(),
+        label: Some(
+            read_trimmed(&dir.join(format!("{}{}_label", kind, n)))
+                .unwrap_or_else(|_| format!("{}{}", kind, n)),
+        ),
+    };
+    for n in inputs(dir, "temp")? {
+        let input = dir.join(format!("temp{}_input", n));
+        // Some are unreadable while their device is powered down.
+        let celsius = match read_millidegrees(&input) {
+            Ok(celsius) => celsius,
+            Err(error) => {
+                tracing::debug!(?input, ?error, "Skipping sensor.");
+                continue;
+            }
+        };
+        let limit = |name: &str| {
+            read_millidegrees(&dir.join(format!("temp{}_{}", n, name)))
+                .ok()
+                .filter(|limit| *limit > 0.0)
+        };
+        reading.temps.push(Temp {
+            id: id("temp", &n),
+            celsius,
+            max: limit("max"),
+            crit: limit("crit"),
+        });
+    }
+    for n in inputs(dir, "fan")? {
+        let input = dir.join(format!("fan{}_input", n));
+        match read_trimmed(&input).map(|rpm| rpm.parse()) {
+            Ok(Ok(rpm)) => reading.fans.push(Fan {
+                id: id("fan", &n),
+                rpm,
+            }),
+            result => {
+                tracing::debug!(?input, ?result, "Skipping fan.");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Limits are among the trip points: "hot" for max, "critical" for crit.
+fn read_thermal_zone(dir: &Path) -> Result<Temp> {
+    let chip = read_trimmed(&dir.join("type"))?;
+    let mut temp = Temp {
+        id: Id { chip, label: None },
+        celsius: read_millidegrees(&dir.join("temp"))?,
+        max: None,
+        crit: None,
+    };
+    for trip in list(dir, "trip_point_")? {
+        let name = trip.to_string_lossy();
+        let Some(base) = name.strip_suffix("_type") else {
+            continue;
+        };
+        let limit = read_millidegrees(Path::new(&format!("{}_temp", base)))
+            .ok()
+            .filter(|limit| *limit > 0.0);
+        match read_trimmed(&trip)?.as_str() {
+            "hot" => temp.max = limit,
+            "critical" => temp.crit = limit,
+            _ => {}
+        }
+    }
+    Ok(temp)
+}
+
+/// All sensors, except for thermal zones already registered as hwmon chips
+/// of the same name, which would otherwise be there twice.
+pub fn read(hwmon: &Path, thermal: &Path) -> Result<Reading> {
+    let mut reading = Reading::default();
+    for dir in list(hwmon, "hwmon")? {
+        if let Err(error) = read_hwmon(&dir, &mut reading) {
+            tracing::warn!(?dir, ?error, "Failed to read hwmon device.");
+        }
+    }
+    let chips: HashSet<String> =
+        reading.temps.iter().map(|t| t.id.chip.clone()).collect();
+    for dir in list(thermal, "thermal_zone")? {
+        match read_thermal_zone(&dir) {
+            Ok(temp) if chips.contains(&temp.id.chip) => {}
+            Ok(temp) => reading.temps.push(temp),
+            Err(error) => {
+                tracing::debug!(?dir, ?error, "Skipping thermal zone.");
+            }
+        }
+    }
+    Ok(reading)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Chip names, like "coretemp", or sensor ids, like
+    /// "coretemp/Package id 0". Empty means all.
+    pub sensors: Vec<String>,
+}
+
+impl Selection {
+    pub fn matches(&self, id: &Id) -> bool {
+        self.sensors.is_empty()
+            || self
+                .sensors
+                .iter()
+                .any(|s| s == &id.chip || s == &id.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl Unit {
+    pub fn from_celsius(self, celsius: f32) -> f32 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn to_celsius(self, degrees: f32) -> f32 {
+        match self {
+            Self::Celsius => degrees,
+            Self::Fahrenheit => (degrees - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub prefix: String,
+    pub selection: Selection,
+    pub unit: Unit,
+
+    /// Display only the hottest of the selected sensors, rather than each.
+    /// Always the case when none were selected.
+    pub max: bool,
+
+    /// Also display fan speeds.
+    pub fans: bool,
+
+    pub alerts: alerts::Settings,
+}
+
+struct State<'a> {
+    settings: &'a Settings,
+    alerts: alerts::Alerts,
+    reading: Option<Reading>,
+}
+
+impl<'a> State<'a> {
+    fn new(settings: &'a Settings) -> Self {
+        Self {
+            settings,
+            alerts: alerts::Alerts::new(&settings.alerts, settings.unit),
+            reading: None,
+        }
+    }
+}
+
+impl<'a> crate::pipeline::State for State<'a> {
+    type Event = Reading;
+
+    fn update(
+        &mut self,
+        mut reading: Self::Event,
+    ) -> Result<Option<Vec<crate::alert::Alert>>> {
+        let selection = &self.settings.selection;
+        reading.temps.retain(|temp| selection.matches(&temp.id));
+        let alerts = self.alerts.update(&reading.temps);
+        self.reading = Some(reading);
+        Ok((!alerts.is_empty()).then_some(alerts))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
+        let Settings {
+            prefix,
+            selection,
+            unit,
+            max,
+            fans,
+            ..
+        } = self.settings;
+        write!(buf, "{}", prefix)?;
+        let temps = self
+            .reading
+            .as_ref()
+            .map(|r| &r.temps[..])
+            .unwrap_or_default();
+        let hottest = temps
+            .iter()
+            .map(|t| t.celsius)
+            .fold(None, |acc: Option<f32>, c| {
+                Some(acc.map_or(c, |a| a.max(c)))
+            });
+        let shown: Vec<f32> = if *max || selection.sensors.is_empty() {
+            hottest.into_iter().collect()
+        } else {
+            temps.iter().map(|t| t.celsius).collect()
+        };
+        if shown.is_empty() {
+            write!(buf, "----")?;
+        }
+        for (i, celsius) in shown.iter().enumerate() {
+            if i > 0 {
+                write!(buf, " ")?;
+            }
+            write!(
+                buf,
+                "{:.0}{}",
+                unit.from_celsius(*celsius).round(),
+                unit.symbol()
+            )?;
+        }
+        if *fans {
+            for fan in self.reading.iter().flat_map(|r| &r.fans) {
+                write!(buf, " {}rpm", fan.rpm)?;
+            }
+        }
+        writeln!(buf)?;
+        Ok(())
+    }
+}
+
+fn reads<'a>(
+    interval: Duration,
+    hwmon: &'a Path,
+    thermal: &'a Path,
+) -> impl Iterator<Item = Reading> + 'a {
+    use crate::clock;
+
+    clock::new(interval).filter_map(|clock::Tick| {
+        match read(hwmon, thermal) {
+            Err(err) => {
+                tracing::error!("Failed to read temperatures: {:?}", err);
+                None
+            }
+            Ok(reading) => Some(reading),
+        }
+    })
+}
+
+pub fn run(settings: &Settings, interval: Duration) -> Result<()> {
+    crate::pipeline::run_to_stdout(
+        reads(interval, Path::new(HWMON), Path::new(THERMAL)),
+        State::new(settings),
+    )
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use super::{
+    alerts::{self, AlertTrigger},
+    Id, Selection, Settings, Temp, Unit,
+};
+
+fn read() -> super::Reading {
+    super::read(
+        Path::new("tests/sys-class-hwmon"),
+        Path::new("tests/sys-class-thermal"),
+    )
+    .unwrap()
+}
+
+fn id(chip: &str, label: Option<&str>) -> Id {
+    Id {
+        chip: chip.to_string(),
+        label: label.map(String::from),
+    }
+}
+
+#[test]
+fn sensors() {
+    let reading = read();
+    let ids: Vec<String> =
+        reading.temps.iter().map(|t| t.id.to_string()).collect();
+    assert_eq!(
+        vec![
+            "acpitz/temp1",
+            "coretemp/Package id 0",
+            "coretemp/Core 0",
+            "coretemp/Core 1",
+            "nvme/Composite",
+            "thinkpad/temp1",
+            // acpitz zone is already there as hwmon.
+            "x86_pkg_temp",
+            "iwlwifi_1",
+        ],
+        ids
+    );
+    assert_eq!(
+        Temp {
+            id: id("nvme", Some("Composite")),
+            celsius: 38.85,
+            max: Some(84.85),
+            crit: Some(89.85),
+        },
+        reading.temps[4]
+    );
+    assert_eq!(
+        Temp {
+            id: id("iwlwifi_1", None),
+            celsius: 42.0,
+            max: Some(95.0),
+            crit: Some(110.0),
+        },
+        reading.temps[7]
+    );
+    assert_eq!(None, reading.temps[6].max);
+    let fans: Vec<(String, u64)> = reading
+        .fans
+        .iter()
+        .map(|f| (f.id.to_string(), f.rpm))
+        .collect();
+    assert_eq!(
+        vec![
+            ("thinkpad/fan1".to_string(), 2100),
+            ("thinkpad/GPU fan".to_string(), 0)
+        ],
+        fans
+    );
+}
+
+#[test]
+fn display() {
+    use crate::pipeline::State;
+
+    let settings = |sensors: &[&str], unit, max, fans| Settings {
+        prefix: "t ".to_string(),
+        selection: Selection {
+            sensors: sensors.iter().map(|s| s.to_string()).collect(),
+        },
+        unit,
+        max,
+        fans,
+        alerts: alerts::Settings::default(),
+    };
+    let mut buf = Vec::new();
+    for settings in [
+        settings(&[], Unit::Celsius, false, false),
+        settings(&["coretemp"], Unit::Celsius, false, false),
+        settings(&["coretemp"], Unit::Celsius, true, true),
+        settings(
+            &["nvme/Composite", "iwlwifi_1"],
+            Unit::Fahrenheit,
+            false,
+            false,
+        ),
+        settings(&["nonesuch"], Unit::Celsius, false, false),
+    ] {
+        let mut state = super::State::new(&settings);
+        state.update(read()).unwrap();
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(
+        "t 63°C\n\
+         t 61°C 58°C 63°C\n\
+         t 63°C 2100rpm 0rpm\n\
+         t 102°F 108°F\n\
+         t ----\n",
+        String::from_utf8(buf).unwrap()
+    );
+}
+
+#[test]
+fn alerts() {
+    let temp = |celsius| Temp {
+        id: id("nvme", Some("Composite")),
+        celsius,
+        max: Some(84.85),
+        crit: Some(89.85),
+    };
+    let settings = alerts::Settings {
+        triggers: vec!["140:hi".parse().unwrap()],
+        sensor_limits: true,
+    };
+    let mut alerts = alerts::Alerts::new(&settings, Unit::Fahrenheit);
+    let counts: Vec<usize> = [
+        40.0, 60.0, // 140°F
+        61.0, 85.0, // Max.
+        90.0, // Crit.
+        80.0, // Not cooled enough to re-arm max.
+        85.0, 79.0, // Re-armed all, but 140°F.
+        90.0, // All at once, so just the highest.
+    ]
+    .iter()
+    .map(|celsius| alerts.update(&[temp(*celsius)]).len())
+    .collect();
+    assert_eq!(vec![0, 1, 0, 1, 1, 0, 0, 0, 1], counts);
+    assert_eq!(
+        AlertTrigger {
+            threshold: 80.5,
+            level: None
+        },
+        "80.5".parse().unwrap()
+    );
+    assert!("hot".parse::<AlertTrigger>().is_err());
+}
@@ -0,0 +1,134 @@
+// User-specified command (suspend, hibernate, lock, etc.) to run when the
+// battery is critically low, unless power is restored within a grace period.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::alert::{self, Alert};
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Executed with "sh -c".
+    pub command: String,
+
+    /// Battery percentage which, when dropped below, triggers the command.
+    pub threshold: u64,
+
+    /// How long to wait for power to be restored before executing.
+    pub grace: Duration,
+
+    /// Of the scheduled command. The spawn function, other than in tests.
+    pub spawn: fn(Scheduled),
+}
+
+/// Command, waiting out its grace period.
+#[derive(Debug)]
+pub struct Scheduled {
+    pub command: String,
+    pub grace: Duration,
+    canceled: Arc<AtomicBool>,
+}
+
+impl Scheduled {
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+
+    /// Executes the command, now, unless canceled.
+    pub fn run(self) {
+        let command = self.command;
+        if self.canceled.load(Ordering::SeqCst) {
+            tracing::info!(?command, "Critical action canceled.");
+            return;
+        }
+        tracing::warn!(?command, "Critical action executing.");
+        match std::process::Command::new("sh")
+            .args(["-c", &command])
+            .status()
+        {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                tracing::error!(?command, ?status, "Critical action failed.");
+            }
+            Err(error) => {
+                tracing::error!(
+                    ?command,
+                    ?error,
+                    "Critical action failed to execute."
+                );
+            }
+        }
+    }
+}
+
+/// Runs the scheduled command on another thread, after its grace period.
+pub fn spawn(scheduled: Scheduled) {
+    std::thread::spawn(move || {
+        std::thread::sleep(scheduled.grace);
+        scheduled.run();
+    });
+}
+
+#[derive(Debug)]
+pub struct Action {
+    settings: Settings,
+    armed: bool,
+    canceled: Option<Arc<AtomicBool>>,
+}
+
+impl Action {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            armed: true,
+            canceled: None,
+        }
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.settings.threshold
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Schedules the command to run once the grace period expires.
+    pub fn trigger(&mut self, pct: u64) -> Alert {
+        self.armed = false;
+        let canceled = Arc::new(AtomicBool::new(false));
+        self.canceled = Some(canceled.clone());
+        let Settings {
+            command,
+            grace,
+            spawn,
+            ..
+        } = self.settings.clone();
+        tracing::warn!(?command, ?grace, "Critical action scheduled.");
+        spawn(Scheduled {
+            command,
+            grace,
+            canceled,
+        });
+        let summary = format!("Battery critical at {}%!", pct);
+        let body = format!(
+            "Will run {:?} in {} seconds, unless plugged-in.",
+            &self.settings.command,
+            self.settings.grace.as_secs()
+        );
+        Alert::new(alert::Level::Hi, &summary, &body)
+    }
+
+    /// Cancels the scheduled command, if any, and re-arms the trigger.
+    pub fn cancel(&mut self) {
+        if let Some(canceled) = self.canceled.take() {
+            canceled.store(true, Ordering::SeqCst);
+        }
+        self.armed = true;
+    }
+}
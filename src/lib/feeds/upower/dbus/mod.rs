@@ -0,0 +1,270 @@
+// Device updates straight from the UPower daemon, rather than scraped from
+// the output of `upower --monitor-detail`.
+//
+// Ref: <https://upower.freedesktop.org/docs/UPower.html>
+// Ref: <https://upower.freedesktop.org/docs/Device.html>
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    proxy::CacheProperties,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+};
+
+use super::msg;
+
+const UPOWER_INTERFACE: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+// Values of the Device.Type property:
+const KIND_LINE_POWER: u32 = 1;
+const KIND_BATTERY: u32 = 2;
+const KIND_UPS: u32 = 3;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    fn enumerate_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower"
+)]
+trait Device {
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn native_path(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn online(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn energy(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn energy_full(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn energy_full_design(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn energy_rate(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn voltage(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn capacity(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn time_to_empty(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn time_to_full(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn charge_start_threshold(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn charge_end_threshold(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn charge_threshold_enabled(&self) -> zbus::Result<bool>;
+}
+
+fn battery_state(n: u32) -> msg::BatteryState {
+    match n {
+        1 => msg::BatteryState::Charging,
+        2 => msg::BatteryState::Discharging,
+        4 => msg::BatteryState::FullyCharged,
+        5 => msg::BatteryState::PendingCharge,
+        n => {
+            tracing::warn!("unexpected battery state: {:?}", n);
+            msg::BatteryState::Unexpected
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn f32_of(x: f64) -> f32 {
+    x as f32
+}
+
+/// Zero means unknown.
+fn seconds(secs: i64) -> Option<Duration> {
+    u64::try_from(secs)
+        .ok()
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Only exposed since UPower 1.90.3, so their absence is not an error.
+fn read_charge_limit(dev: &DeviceProxyBlocking) -> Option<msg::ChargeLimit> {
+    if !dev.charge_threshold_enabled().ok()? {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let pct = |n: u32| n as f32;
+    msg::ChargeLimit::new(
+        dev.charge_start_threshold().ok().map(pct),
+        dev.charge_end_threshold().ok().map(pct),
+    )
+}
+
+fn read(conn: &Connection, path: &ObjectPath) -> Result<Option<msg::Msg>> {
+    let dev = DeviceProxyBlocking::builder(conn)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()?;
+    // Same as with "upower --dump": prefer native-path, but the aggregate
+    // DisplayDevice does not have one.
+    let path = match dev.native_path()? {
+        native_path if native_path.is_empty() => path.to_string(),
+        native_path => native_path,
+    };
+    let msg = match dev.kind()? {
+        KIND_LINE_POWER => Some(msg::Msg::LinePower(msg::LinePower {
+            path,
+            online: dev.online()?,
+        })),
+        KIND_BATTERY => {
+            let battery = msg::Battery {
+                path,
+                state: battery_state(dev.state()?),
+                energy: f32_of(dev.energy()?),
+                energy_full: f32_of(dev.energy_full()?),
+                energy_full_design: Some(f32_of(dev.energy_full_design()?)),
+                energy_rate: Some(f32_of(dev.energy_rate()?)),
+                voltage: Some(f32_of(dev.voltage()?)),
+                capacity: Some(f32_of(dev.capacity()?)),
+                time_to_empty: seconds(dev.time_to_empty()?),
+                time_to_full: seconds(dev.time_to_full()?),
+                charge_limit: read_charge_limit(&dev),
+            };
+            battery.check()?;
+            Some(msg::Msg::Battery(battery))
+        }
+        KIND_UPS => Some(msg::Msg::Ups(msg::Ups {
+            path,
+            state: battery_state(dev.state()?),
+            percentage: f32_of(dev.percentage()?),
+            time_to_empty: seconds(dev.time_to_empty()?),
+        })),
+        kind if msg::KINDS
+            .get(kind as usize)
+            .is_some_and(|name| msg::is_peripheral_kind(name)) =>
+        {
+            Some(msg::Msg::Peripheral(msg::Peripheral {
+                path,
+                kind: msg::KINDS[kind as usize].to_string(),
+                percentage: f32_of(dev.percentage()?),
+            }))
+        }
+        kind => {
+            tracing::trace!(?path, kind, "Ignoring device of unused kind.");
+            None
+        }
+    };
+    Ok(msg)
+}
+
+#[derive(Debug)]
+enum Event {
+    /// Enumerated, added or with its properties changed.
+    Changed(OwnedObjectPath),
+    Removed(OwnedObjectPath),
+}
+
+/// Of those of interest, which all come from the UPower service, or from its
+/// devices.
+fn event(signal: &zbus::Message) -> Option<Event> {
+    let header = signal.header();
+    let body = signal.body();
+    match (header.interface()?.as_str(), header.member()?.as_str()) {
+        ("org.freedesktop.DBus.Properties", "PropertiesChanged") => {
+            // As opposed to the properties of the UPower service itself.
+            let (interface, _, _): (
+                String,
+                HashMap<String, OwnedValue>,
+                Vec<String>,
+            ) = body.deserialize().ok()?;
+            let path = OwnedObjectPath::from(header.path()?.to_owned());
+            (interface == DEVICE_INTERFACE).then_some(Event::Changed(path))
+        }
+        (UPOWER_INTERFACE, "DeviceAdded") => {
+            body.deserialize().ok().map(Event::Changed)
+        }
+        (UPOWER_INTERFACE, "DeviceRemoved") => {
+            body.deserialize().ok().map(Event::Removed)
+        }
+        _ => None,
+    }
+}
+
+/// Current state of all devices, followed by their changes, additions and
+/// removals.
+pub fn messages(conn: Connection) -> Result<impl Iterator<Item = msg::Msg>> {
+    // XXX Subscribing before enumerating, so that no changes are missed.
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .path_namespace(UPOWER_PATH)?
+        .build();
+    let signals = MessageIterator::for_match_rule(rule, &conn, None)?;
+    let devices = UPowerProxyBlocking::new(&conn)?.enumerate_devices()?;
+    tracing::debug!(?devices, "Enumerated devices.");
+    let events = signals.filter_map(|signal_result| match signal_result {
+        Err(error) => {
+            tracing::error!(?error, "Failed to receive signal.");
+            None
+        }
+        Ok(signal) => event(&signal),
+    });
+    // Removed devices can no longer be asked for their native path, which
+    // is what we know them by.
+    let mut paths: HashMap<OwnedObjectPath, String> = HashMap::new();
+    let messages = devices
+        .into_iter()
+        .map(Event::Changed)
+        .chain(events)
+        .filter_map(move |event| match event {
+            Event::Changed(path) => match read(&conn, &path) {
+                Err(error) => {
+                    tracing::error!(?error, ?path, "Failed to read device.");
+                    None
+                }
+                Ok(msg_opt) => {
+                    if let Some(msg) = &msg_opt {
+                        paths.insert(path, msg.path().to_string());
+                    }
+                    msg_opt
+                }
+            },
+            Event::Removed(path) => {
+                tracing::debug!(?path, "Device removed.");
+                paths.remove(&path).map(msg::Msg::Removed)
+            }
+        });
+    Ok(messages)
+}
+
+pub fn messages_from_system_bus() -> Result<impl Iterator<Item = msg::Msg>> {
+    messages(Connection::system()?)
+}
@@ -0,0 +1,363 @@
+use std::{
+    io::BufRead, // .lines()
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+use zbus::{
+    blocking::connection,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, OwnedObjectPath},
+};
+
+use super::super::msg;
+
+const AC: &str = "/org/freedesktop/UPower/devices/line_power_AC";
+const BAT0: &str = "/org/freedesktop/UPower/devices/battery_BAT0";
+const MOUSE: &str = "/org/freedesktop/UPower/devices/mouse_0";
+const HEADSET: &str = "/org/freedesktop/UPower/devices/headset_0";
+
+/// For each message, so that a missing one fails the test, rather than
+/// hang it.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Private message bus, so that we do not depend on (or disturb) whatever
+/// may or may not be running on the host.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    fn start() -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon unavailable");
+        let stdout = daemon.stdout.take().unwrap();
+        let address = std::io::BufReader::new(stdout)
+            .lines()
+            .next()
+            .unwrap()
+            .unwrap();
+        Self { daemon, address }
+    }
+
+    fn connect(&self) -> connection::Builder<'_> {
+        connection::Builder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+struct StubUPower {
+    devices: Vec<OwnedObjectPath>,
+}
+
+#[zbus::interface(name = "org.freedesktop.UPower")]
+impl StubUPower {
+    fn enumerate_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices.clone()
+    }
+
+    #[zbus(signal)]
+    async fn device_added(
+        emitter: &SignalEmitter<'_>,
+        device: ObjectPath<'_>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn device_removed(
+        emitter: &SignalEmitter<'_>,
+        device: ObjectPath<'_>,
+    ) -> zbus::Result<()>;
+}
+
+struct StubDevice {
+    kind: u32,
+    native_path: String,
+    online: bool,
+    state: u32,
+    energy: f64,
+    energy_full: f64,
+    energy_rate: f64,
+    time_to_empty: i64,
+    percentage: f64,
+}
+
+impl StubDevice {
+    fn line_power(native_path: &str, online: bool) -> Self {
+        Self {
+            kind: super::KIND_LINE_POWER,
+            native_path: native_path.to_string(),
+            online,
+            state: 0,
+            energy: 0.0,
+            energy_full: 0.0,
+            energy_rate: 0.0,
+            time_to_empty: 0,
+            percentage: 0.0,
+        }
+    }
+
+    fn battery(native_path: &str, state: u32, energy: f64) -> Self {
+        Self {
+            kind: super::KIND_BATTERY,
+            native_path: native_path.to_string(),
+            online: false,
+            state,
+            energy,
+            energy_full: 80.0,
+            energy_rate: 10.0,
+            time_to_empty: 6 * 60 * 60,
+            percentage: energy / 80.0 * 100.0,
+        }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.UPower.Device")]
+impl StubDevice {
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> u32 {
+        self.kind
+    }
+
+    #[zbus(property)]
+    fn native_path(&self) -> String {
+        self.native_path.clone()
+    }
+
+    #[zbus(property)]
+    fn online(&self) -> bool {
+        self.online
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+
+    #[zbus(property)]
+    fn energy(&self) -> f64 {
+        self.energy
+    }
+
+    #[zbus(property)]
+    fn energy_full(&self) -> f64 {
+        self.energy_full
+    }
+
+    #[zbus(property)]
+    fn energy_full_design(&self) -> f64 {
+        100.0
+    }
+
+    #[zbus(property)]
+    fn energy_rate(&self) -> f64 {
+        self.energy_rate
+    }
+
+    #[zbus(property)]
+    fn voltage(&self) -> f64 {
+        12.5
+    }
+
+    #[zbus(property)]
+    fn capacity(&self) -> f64 {
+        80.0
+    }
+
+    #[zbus(property)]
+    fn time_to_empty(&self) -> i64 {
+        self.time_to_empty
+    }
+
+    #[zbus(property)]
+    fn time_to_full(&self) -> i64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn percentage(&self) -> f64 {
+        self.percentage
+    }
+}
+
+fn path(p: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(p).unwrap()
+}
+
+/// Received on another thread, so that they can be waited for with a
+/// timeout.
+fn receiver(
+    messages: impl Iterator<Item = msg::Msg> + Send + 'static,
+) -> mpsc::Receiver<msg::Msg> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for msg in messages {
+            if sender.send(msg).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[test]
+#[ignore = "requires dbus-daemon"]
+fn enumerate_and_monitor() {
+    let bus = Bus::start();
+    let service = bus
+        .connect()
+        .name("org.freedesktop.UPower")
+        .unwrap()
+        .serve_at(
+            "/org/freedesktop/UPower",
+            StubUPower {
+                devices: vec![path(AC), path(BAT0), path(MOUSE)],
+            },
+        )
+        .unwrap()
+        .serve_at(AC, StubDevice::line_power("AC", false))
+        .unwrap()
+        .serve_at(BAT0, StubDevice::battery("BAT0", 2, 60.0))
+        .unwrap()
+        .serve_at(
+            MOUSE,
+            StubDevice {
+                kind: 5,
+                percentage: 55.0,
+                ..StubDevice::battery("hidpp_battery_0", 2, 1.0)
+            },
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+    let client = bus.connect().build().unwrap();
+    let messages = receiver(super::messages(client).unwrap());
+    let next = || messages.recv_timeout(TIMEOUT).ok();
+
+    assert_eq!(
+        Some(msg::Msg::LinePower(msg::LinePower {
+            path: "AC".to_string(),
+            online: false,
+        })),
+        next()
+    );
+    assert_eq!(
+        Some(msg::Msg::Battery(msg::Battery {
+            path: "BAT0".to_string(),
+            state: msg::BatteryState::Discharging,
+            energy: 60.0,
+            energy_full: 80.0,
+            energy_full_design: Some(100.0),
+            energy_rate: Some(10.0),
+            voltage: Some(12.5),
+            capacity: Some(80.0),
+            time_to_empty: Some(Duration::from_secs(6 * 60 * 60)),
+            time_to_full: None,
+            charge_limit: None,
+        })),
+        next()
+    );
+    assert_eq!(
+        Some(msg::Msg::Peripheral(msg::Peripheral {
+            path: "hidpp_battery_0".to_string(),
+            kind: "mouse".to_string(),
+            percentage: 55.0,
+        })),
+        next()
+    );
+
+    let ac = service
+        .object_server()
+        .interface::<_, StubDevice>(AC)
+        .unwrap();
+    ac.get_mut().online = true;
+    zbus::block_on(ac.get().online_changed(ac.signal_emitter())).unwrap();
+    assert_eq!(
+        Some(msg::Msg::LinePower(msg::LinePower {
+            path: "AC".to_string(),
+            online: true,
+        })),
+        next()
+    );
+
+    let bat = service
+        .object_server()
+        .interface::<_, StubDevice>(BAT0)
+        .unwrap();
+    {
+        let mut bat = bat.get_mut();
+        bat.state = 1;
+        bat.energy = 61.5;
+        bat.time_to_empty = 0;
+    }
+    zbus::block_on(bat.get().energy_changed(bat.signal_emitter())).unwrap();
+    assert_eq!(
+        Some(msg::Msg::Battery(msg::Battery {
+            path: "BAT0".to_string(),
+            state: msg::BatteryState::Charging,
+            energy: 61.5,
+            energy_full: 80.0,
+            energy_full_design: Some(100.0),
+            energy_rate: Some(10.0),
+            voltage: Some(12.5),
+            capacity: Some(80.0),
+            time_to_empty: None,
+            time_to_full: None,
+            charge_limit: None,
+        })),
+        next()
+    );
+
+    let upower = service
+        .object_server()
+        .interface::<_, StubUPower>("/org/freedesktop/UPower")
+        .unwrap();
+    service
+        .object_server()
+        .at(
+            HEADSET,
+            StubDevice {
+                kind: 17,
+                percentage: 40.0,
+                ..StubDevice::battery("/org/bluez/hci0/dev_88_C9", 2, 1.0)
+            },
+        )
+        .unwrap();
+    zbus::block_on(StubUPower::device_added(
+        upower.signal_emitter(),
+        ObjectPath::try_from(HEADSET).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(
+        Some(msg::Msg::Peripheral(msg::Peripheral {
+            path: "/org/bluez/hci0/dev_88_C9".to_string(),
+            kind: "headset".to_string(),
+            percentage: 40.0,
+        })),
+        next()
+    );
+
+    service
+        .object_server()
+        .remove::<StubDevice, _>(MOUSE)
+        .unwrap();
+    zbus::block_on(StubUPower::device_removed(
+        upower.signal_emitter(),
+        ObjectPath::try_from(MOUSE).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(
+        Some(msg::Msg::Removed("hidpp_battery_0".to_string())),
+        next()
+    );
+}
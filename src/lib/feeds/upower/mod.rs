@@ -1,14 +1,99 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use anyhow::Result;
 
+use crate::alert;
+
+pub mod critical;
+mod dbus;
 mod msg;
 mod state;
+mod sysfs;
 
 #[cfg(test)]
 mod tests;
 
-pub fn run(prefix: &str, alert_triggers: &[u64]) -> Result<()> {
-    crate::pipeline::run_to_stdout(
-        msg::Messages::from_run()?,
-        state::State::new(prefix, alert_triggers)?,
-    )
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Backend {
+    /// Parse the output of "upower --dump" and "upower --monitor-detail".
+    Text,
+
+    /// Talk to the UPower daemon directly, over the system D-Bus.
+    Dbus,
+
+    /// Read the kernel's power_supply class directly, without upowerd.
+    Sysfs,
+}
+
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub prefix: String,
+
+    /// Battery percentages which, when dropped below, trigger an alert.
+    pub alert_triggers: Vec<alert::AlertTrigger>,
+
+    /// Alert when the estimated time left on battery drops below this.
+    pub alert_time_left: Option<Duration>,
+
+    /// Alert, once, when battery health (percentage of design capacity
+    /// left) is below this.
+    pub alert_health: Option<u64>,
+
+    /// Alert when the power is plugged-in or unplugged.
+    pub alert_plug: bool,
+
+    /// Alert when a battery becomes fully charged.
+    pub alert_full: bool,
+
+    /// Alert when plugged-in, but a battery keeps discharging for this long,
+    /// on the first update after that.
+    pub alert_not_charging: Option<Duration>,
+
+    /// Display the estimated time left until empty or full.
+    pub show_time: bool,
+
+    /// Display the current power draw (or charge) in watts.
+    pub show_power: bool,
+
+    /// Display the battery health.
+    pub show_health: bool,
+
+    /// Display the percentage of each battery, after the combined one.
+    pub show_batteries: bool,
+
+    /// Display the percentage of each peripheral (mouse, headset, etc).
+    pub show_peripherals: bool,
+
+    /// Alert when a peripheral's battery percentage drops below this.
+    pub alert_peripheral: Option<u64>,
+
+    /// Where to (re)write the details, as JSON, on each update.
+    pub summary_file: Option<PathBuf>,
+
+    /// What to do when the battery is about to die.
+    pub critical: Option<critical::Settings>,
+}
+
+pub fn run(
+    backend: Backend,
+    poll_interval: Duration,
+    settings: &Settings,
+) -> Result<()> {
+    let state = state::State::new(settings)?;
+    match backend {
+        Backend::Text => {
+            crate::pipeline::run_to_stdout(msg::Messages::from_run()?, state)
+        }
+        Backend::Dbus => crate::pipeline::run_to_stdout(
+            dbus::messages_from_system_bus()?,
+            state,
+        ),
+        Backend::Sysfs => crate::pipeline::run_to_stdout(
+            sysfs::messages(Path::new(sysfs::POWER_SUPPLY), poll_interval)?,
+            state,
+        ),
+    }
 }
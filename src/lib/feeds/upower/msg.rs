@@ -1,5 +1,66 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, Context, Result};
 
+/// Device kind names, as "upower --dump" prints them, indexed by the values
+/// of the Device.Type property, as UPower's D-Bus interface exposes them.
+pub const KINDS: [&str; 29] = [
+    "unknown",
+    "line-power",
+    "battery",
+    "ups",
+    "monitor",
+    "mouse",
+    "keyboard",
+    "pda",
+    "phone",
+    "media-player",
+    "tablet",
+    "computer",
+    "gaming-input",
+    "pen",
+    "touchpad",
+    "modem",
+    "network",
+    "headset",
+    "speakers",
+    "headphones",
+    "video",
+    "other-audio",
+    "remote-control",
+    "printer",
+    "scanner",
+    "camera",
+    "wearable",
+    "toy",
+    "bluetooth-generic",
+];
+
+/// Battery-powered devices of ours, which have their own batteries, rather
+/// than power us. Not the likes of monitors or printers, which UPower may
+/// report a percentage of, but which are not battery-powered.
+pub const PERIPHERAL_KINDS: [&str; 15] = [
+    "mouse",
+    "keyboard",
+    "touchpad",
+    "pen",
+    "gaming-input",
+    "tablet",
+    "phone",
+    "pda",
+    "media-player",
+    "headset",
+    "headphones",
+    "speakers",
+    "remote-control",
+    "wearable",
+    "toy",
+];
+
+pub fn is_peripheral_kind(kind: &str) -> bool {
+    PERIPHERAL_KINDS.contains(&kind)
+}
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum BatteryState {
     PendingCharge,
@@ -27,12 +88,101 @@ impl std::str::FromStr for BatteryState {
     }
 }
 
+/// Charge thresholds (AKA conservation mode), at which the firmware stops
+/// charging, to spare the battery. Percentages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChargeLimit {
+    pub start: Option<f32>,
+    pub end: f32,
+}
+
+impl ChargeLimit {
+    /// An end of 100% (or an unset 0) is no limit at all.
+    pub fn new(start: Option<f32>, end: Option<f32>) -> Option<Self> {
+        let end = end.filter(|end| *end > 0.0 && *end < 100.0)?;
+        Some(Self {
+            start: start.filter(|start| *start > 0.0 && *start < end),
+            end,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Battery {
     pub path: String,
     pub state: BatteryState,
     pub energy: f32,
     pub energy_full: f32,
+    pub energy_full_design: Option<f32>,
+    pub energy_rate: Option<f32>, // W
+    pub voltage: Option<f32>,     // V
+    pub capacity: Option<f32>,    // %, of energy_full_design.
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+    pub charge_limit: Option<ChargeLimit>,
+}
+
+impl Battery {
+    pub fn percentage(&self) -> Option<u64> {
+        crate::math::percentage_floor(self.energy, self.energy_full)
+    }
+
+    /// Plugged-in, but charging is held back by the charge limit, rather
+    /// than by anything wrong. The firmware resumes charging only once
+    /// dropped below the start threshold, if there is one.
+    pub fn is_held_at_limit(&self) -> bool {
+        match (self.state, self.charge_limit, self.percentage()) {
+            (BatteryState::PendingCharge, Some(limit), Some(pct)) => {
+                let floor = limit.start.unwrap_or(limit.end - 1.0);
+                #[allow(clippy::cast_precision_loss)]
+                let pct = pct as f32;
+                pct >= floor
+            }
+            _ => false,
+        }
+    }
+
+    /// Percentage of the design capacity which is still left.
+    pub fn health(&self) -> Option<f32> {
+        match self.energy_full_design {
+            Some(design) if design > 0.0 => {
+                Some(self.energy_full / design * 100.0)
+            }
+            _ => self.capacity,
+        }
+    }
+
+    pub fn check(&self) -> Result<()> {
+        let Self {
+            path,
+            energy,
+            energy_full,
+            ..
+        } = self;
+        if energy > energy_full {
+            return Err(anyhow!(
+                "energy exceeds energy_full ({} > {}) for battery path: {:?}",
+                energy,
+                energy_full,
+                path
+            ));
+        }
+        if *energy < 0.0 {
+            return Err(anyhow!(
+                "negative energy ({}) for battery path: {:?}",
+                energy,
+                path
+            ));
+        };
+        if *energy_full < 0.0 {
+            return Err(anyhow!(
+                "negative energy_full ({}) for battery path: {:?}",
+                energy_full,
+                path
+            ));
+        };
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -41,6 +191,24 @@ pub struct LinePower {
     pub online: bool,
 }
 
+/// Peripherals only report a percentage, which is often just an
+/// approximation of a coarse level, rather than energy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peripheral {
+    pub path: String,
+    pub kind: String,
+    pub percentage: f32,
+}
+
+/// Uninterruptible power supply, which keeps us running when mains fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ups {
+    pub path: String,
+    pub state: BatteryState,
+    pub percentage: f32,
+    pub time_to_empty: Option<Duration>,
+}
+
 #[derive(Debug)]
 enum MsgIntermediate {
     Device {
@@ -48,22 +216,139 @@ enum MsgIntermediate {
         native_path: Option<String>,
     },
     LinePower(LinePower),
-    Battery {
+    Battery(BatteryFields),
+    Peripheral {
         path: String,
-        state: Option<BatteryState>,
-        energy: Option<f32>,
-        energy_full: Option<f32>,
+        kind: String,
+        percentage: Option<f32>,
     },
+    Ups(UpsFields),
     Unhandled,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default)]
+struct BatteryFields {
+    path: String,
+    state: Option<BatteryState>,
+    energy: Option<f32>,
+    energy_full: Option<f32>,
+    energy_full_design: Option<f32>,
+    energy_rate: Option<f32>,
+    voltage: Option<f32>,
+    capacity: Option<f32>,
+    time_to_empty: Option<Duration>,
+    time_to_full: Option<Duration>,
+    charge_start_threshold: Option<f32>,
+    charge_end_threshold: Option<f32>,
+    charge_threshold_enabled: Option<bool>,
+}
+
+impl BatteryFields {
+    fn into_battery(self) -> Result<Battery> {
+        let state = self
+            .state
+            .ok_or_else(|| anyhow!("missing state: {:?}", self))?;
+        let energy = self
+            .energy
+            .ok_or_else(|| anyhow!("missing energy: {:?}", self))?;
+        let energy_full = self
+            .energy_full
+            .ok_or_else(|| anyhow!("missing energy_full: {:?}", self))?;
+        let battery = Battery {
+            path: self.path,
+            state,
+            energy,
+            energy_full,
+            energy_full_design: self.energy_full_design,
+            energy_rate: self.energy_rate,
+            voltage: self.voltage,
+            capacity: self.capacity,
+            time_to_empty: self.time_to_empty,
+            time_to_full: self.time_to_full,
+            charge_limit: match self.charge_threshold_enabled {
+                Some(false) => None,
+                Some(true) | None => ChargeLimit::new(
+                    self.charge_start_threshold,
+                    self.charge_end_threshold,
+                ),
+            },
+        };
+        battery.check()?;
+        Ok(battery)
+    }
+}
+
+#[derive(Debug, Default)]
+struct UpsFields {
+    path: String,
+    state: Option<BatteryState>,
+    percentage: Option<f32>,
+    time_to_empty: Option<Duration>,
+}
+
+impl UpsFields {
+    fn into_ups(self) -> Result<Ups> {
+        let state = self
+            .state
+            .ok_or_else(|| anyhow!("missing state: {:?}", self))?;
+        let percentage = self
+            .percentage
+            .ok_or_else(|| anyhow!("missing percentage: {:?}", self))?;
+        Ok(Ups {
+            path: self.path,
+            state,
+            percentage,
+            time_to_empty: self.time_to_empty,
+        })
+    }
+}
+
+fn parse_num(qty: &str, line: &str) -> Result<f32> {
+    qty.parse::<f32>().context(format!("line: {:?}", line))
+}
+
+fn parse_duration(qty: &str, units: &str, line: &str) -> Result<Duration> {
+    let secs = match units {
+        "seconds" => 1.0,
+        "minutes" => 60.0,
+        "hours" => 60.0 * 60.0,
+        "days" => 24.0 * 60.0 * 60.0,
+        _ => {
+            return Err(anyhow!("Unexpected time units: {:?}", units)
+                .context(format!("line: {:?}", line)))
+        }
+    };
+    let qty = parse_num(qty, line)?;
+    // Displayed with only 1 decimal anyway, so sub-second precision would
+    // only be noise from the float math.
+    Duration::try_from_secs_f32((qty * secs).round())
+        .context(format!("line: {:?}", line))
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Msg {
     LinePower(LinePower),
     Battery(Battery),
+    Peripheral(Peripheral),
+    Ups(Ups),
+
+    /// Device, by path, which is gone, like an unplugged peripheral or a
+    /// pulled battery.
+    Removed(String),
 }
 
 impl Msg {
+    /// Of the device it is about.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::LinePower(LinePower { path, .. })
+            | Self::Battery(Battery { path, .. })
+            | Self::Peripheral(Peripheral { path, .. })
+            | Self::Ups(Ups { path, .. })
+            | Self::Removed(path) => path,
+        }
+    }
+
     fn from_lines(
         mut lines: impl Iterator<Item = String>,
     ) -> Result<Option<Self>> {
@@ -75,64 +360,46 @@ impl Msg {
                     let fields =
                         line.split_whitespace().collect::<Vec<&str>>();
                     tracing::trace!("Line fields: {:?}", &fields);
-                    match (line.starts_with([' ', '\t']), &msg, &fields[..]) {
+                    match (
+                        line.starts_with([' ', '\t']),
+                        &mut msg,
+                        &fields[..],
+                    ) {
                         // end msg
                         (false, _, [] | ["Monitoring", "activity", ..]) => {
-                            match &msg {
+                            match msg.take() {
                                 Some(MsgIntermediate::LinePower(lp)) => {
-                                    return Ok(Some(Msg::LinePower(
-                                        lp.clone(),
-                                    )))
+                                    return Ok(Some(Msg::LinePower(lp)))
                                 }
-                                Some(
-                                    imsg @ MsgIntermediate::Battery {
-                                        path,
-                                        state,
-                                        energy,
-                                        energy_full,
-                                    },
-                                ) => {
-                                    let state = state.ok_or_else(|| {
-                                        anyhow!("missing state: {:?}", imsg)
-                                    })?;
-                                    let energy = energy.ok_or_else(|| {
-                                        anyhow!("missing energy: {:?}", imsg)
-                                    })?;
-                                    let energy_full = energy_full
-                                        .ok_or_else(|| {
+                                Some(MsgIntermediate::Battery(fields)) => {
+                                    let battery = fields.into_battery()?;
+                                    return Ok(Some(Msg::Battery(battery)));
+                                }
+                                Some(MsgIntermediate::Ups(fields)) => {
+                                    let ups = fields.into_ups()?;
+                                    return Ok(Some(Msg::Ups(ups)));
+                                }
+                                Some(MsgIntermediate::Peripheral {
+                                    path,
+                                    kind,
+                                    percentage,
+                                }) => {
+                                    let percentage =
+                                        percentage.ok_or_else(|| {
                                             anyhow!(
-                                                "missing energy_full: {:?}",
-                                                imsg
+                                                "missing percentage: {:?}",
+                                                path
                                             )
                                         })?;
-                                    if energy > energy_full {
-                                        return Err(anyhow!(
-                                            "energy exceeds energy_full ({} > {}) for battery path: {:?}",
-                                            energy, energy_full, &path
-                                        ));
-                                    }
-                                    if energy < 0.0 {
-                                        return Err(anyhow!(
-                                            "negative energy ({}) for battery path: {:?}",
-                                            energy, &path
-                                        ));
-                                    };
-                                    if energy_full < 0.0 {
-                                        return Err(anyhow!(
-                                            "negative energy_full ({}) for battery path: {:?}",
-                                            energy_full, &path
-                                        ));
-                                    };
-                                    let msg = Msg::Battery(Battery {
-                                        path: path.clone(),
-                                        state,
-                                        energy,
-                                        energy_full,
-                                    });
-                                    return Ok(Some(msg));
+                                    return Ok(Some(Msg::Peripheral(
+                                        Peripheral {
+                                            path,
+                                            kind,
+                                            percentage,
+                                        },
+                                    )));
                                 }
-                                Some(_) => msg = None,
-                                None => (),
+                                Some(_) | None => (),
                             }
                         }
 
@@ -184,80 +451,116 @@ impl Msg {
                             }),
                             ["battery"],
                         ) => {
-                            msg = Some(MsgIntermediate::Battery {
-                                path: match native_path {
-                                    None => path.to_string(),
-                                    Some(path) => path.to_string(),
+                            msg = Some(MsgIntermediate::Battery(
+                                BatteryFields {
+                                    path: match native_path {
+                                        None => path.to_string(),
+                                        Some(path) => path.to_string(),
+                                    },
+                                    ..BatteryFields::default()
                                 },
-                                state: None,
-                                energy: None,
-                                energy_full: None,
-                            });
+                            ));
                         }
                         (
                             true,
-                            Some(MsgIntermediate::Battery {
-                                path: p,
-                                state: _,
-                                energy: e,
-                                energy_full: ef,
-                            }),
+                            Some(MsgIntermediate::Battery(b)),
                             ["state:", state],
                         ) => {
-                            msg = Some(MsgIntermediate::Battery {
-                                path: p.clone(),
-                                state: Some(
-                                    state.parse::<BatteryState>().context(
-                                        format!("line: {:?}", &line),
-                                    )?,
-                                ),
-                                energy: *e,
-                                energy_full: *ef,
-                            });
+                            b.state = Some(
+                                state
+                                    .parse::<BatteryState>()
+                                    .context(format!("line: {:?}", &line))?,
+                            );
                         }
                         (
                             true,
-                            Some(MsgIntermediate::Battery {
-                                path: p,
-                                state: s,
-                                energy: _,
-                                energy_full: ef,
-                            }),
+                            Some(MsgIntermediate::Battery(b)),
                             ["energy:", qty, _units],
                         ) => {
-                            msg = Some(MsgIntermediate::Battery {
-                                path: p.clone(),
-                                state: *s,
-                                energy: Some(
-                                    qty.parse::<f32>().context(format!(
-                                        "line: {:?}",
-                                        &line
-                                    ))?,
-                                ),
-                                energy_full: *ef,
-                            });
+                            b.energy = Some(parse_num(qty, &line)?);
                         }
                         (
                             true,
-                            Some(MsgIntermediate::Battery {
-                                path: p,
-                                state: s,
-                                energy: e,
-                                energy_full: _,
-                            }),
+                            Some(MsgIntermediate::Battery(b)),
                             ["energy-full:", qty, _units],
                         ) => {
-                            msg = Some(MsgIntermediate::Battery {
-                                path: p.clone(),
-                                state: *s,
-                                energy: *e,
-                                energy_full: Some(
-                                    qty.parse::<f32>().context(format!(
-                                        "line: {:?}",
-                                        &line
-                                    ))?,
-                                ),
-                            });
+                            b.energy_full = Some(parse_num(qty, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["energy-full-design:", qty, _units],
+                        ) => {
+                            b.energy_full_design =
+                                Some(parse_num(qty, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["energy-rate:", qty, _units],
+                        ) => {
+                            b.energy_rate = Some(parse_num(qty, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["voltage:", qty, _units],
+                        ) => {
+                            b.voltage = Some(parse_num(qty, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["capacity:", pct],
+                        ) => {
+                            b.capacity = Some(parse_num(
+                                pct.trim_end_matches('%'),
+                                &line,
+                            )?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["time", "to", "empty:", qty, units],
+                        ) => {
+                            b.time_to_empty =
+                                Some(parse_duration(qty, units, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["time", "to", "full:", qty, units],
+                        ) => {
+                            b.time_to_full =
+                                Some(parse_duration(qty, units, &line)?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["charge-start-threshold:", pct],
+                        ) => {
+                            b.charge_start_threshold = Some(parse_num(
+                                pct.trim_end_matches('%'),
+                                &line,
+                            )?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["charge-end-threshold:", pct],
+                        ) => {
+                            b.charge_end_threshold = Some(parse_num(
+                                pct.trim_end_matches('%'),
+                                &line,
+                            )?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Battery(b)),
+                            ["charge-threshold-enabled:", enabled],
+                        ) => {
+                            b.charge_threshold_enabled =
+                                Some(*enabled == "yes");
                         }
                         // -- END battery
 
@@ -308,6 +611,90 @@ impl Msg {
                         }
                         // -- END line-power
 
+                        // -- BEGIN ups
+                        (
+                            true,
+                            Some(MsgIntermediate::Device {
+                                path,
+                                native_path,
+                            }),
+                            ["ups"],
+                        ) => {
+                            msg = Some(MsgIntermediate::Ups(UpsFields {
+                                path: match native_path {
+                                    None => path.to_string(),
+                                    Some(path) => path.to_string(),
+                                },
+                                ..UpsFields::default()
+                            }));
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Ups(u)),
+                            ["state:", state],
+                        ) => {
+                            u.state = Some(
+                                state
+                                    .parse::<BatteryState>()
+                                    .context(format!("line: {:?}", &line))?,
+                            );
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Ups(u)),
+                            ["percentage:", pct, ..],
+                        ) => {
+                            u.percentage = Some(parse_num(
+                                pct.trim_end_matches('%'),
+                                &line,
+                            )?);
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Ups(u)),
+                            ["time", "to", "empty:", qty, units],
+                        ) => {
+                            u.time_to_empty =
+                                Some(parse_duration(qty, units, &line)?);
+                        }
+                        // -- END ups
+
+                        // -- BEGIN peripheral
+                        (
+                            true,
+                            Some(MsgIntermediate::Device {
+                                path,
+                                native_path,
+                            }),
+                            [kind],
+                        ) if is_peripheral_kind(kind) => {
+                            msg = Some(MsgIntermediate::Peripheral {
+                                path: match native_path {
+                                    None => path.to_string(),
+                                    Some(path) => path.to_string(),
+                                },
+                                kind: kind.to_string(),
+                                percentage: None,
+                            });
+                        }
+                        (
+                            true,
+                            Some(MsgIntermediate::Peripheral {
+                                percentage,
+                                ..
+                            }),
+                            // Followed by "(should be ignored)" when it is
+                            // only an approximation of a coarse level, but
+                            // that is still better than nothing.
+                            ["percentage:", pct, ..],
+                        ) => {
+                            *percentage = Some(parse_num(
+                                pct.trim_end_matches('%'),
+                                &line,
+                            )?);
+                        }
+                        // -- END peripheral
+
                         // unused
                         (true, Some(_), _) => {
                             tracing::trace!(
@@ -1,10 +1,18 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Result};
 
 use crate::alert::{self, Alert};
 
-use super::msg;
+use super::{critical, msg, Settings};
+
+/// Weight of the newest sample in the exponential moving average of the
+/// energy rate.
+const RATE_SMOOTHING: f32 = 0.25;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Direction {
@@ -25,28 +33,151 @@ impl Direction {
     }
 }
 
+/// Of the alert, when not given, by how low the battery is.
+pub fn level(trigger: &alert::AlertTrigger) -> alert::Level {
+    trigger.level(alert::Direction::Falling, 50, 25)
+}
+
+/// H:MM
+fn fmt_time(time: Duration) -> String {
+    let minutes = time.as_secs() / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Summary<'a> {
+    direction: char,
+    plugged_in: bool,
+    mains_failed: bool,
+    percentage: Option<u64>,
+    time_left_secs: Option<u64>,
+    power_w: Option<f32>,
+    health_pct: Option<f32>,
+    batteries: Vec<BatterySummary<'a>>,
+    peripherals: Vec<PeripheralSummary<'a>>,
+    ups: Vec<UpsSummary<'a>>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct BatterySummary<'a> {
+    path: &'a str,
+    state: String,
+    percentage: Option<u64>,
+    energy_wh: f32,
+    energy_full_wh: f32,
+    energy_full_design_wh: Option<f32>,
+    energy_rate_w: Option<f32>,
+    voltage_v: Option<f32>,
+    health_pct: Option<f32>,
+    charge_start_threshold: Option<f32>,
+    charge_end_threshold: Option<f32>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct PeripheralSummary<'a> {
+    path: &'a str,
+    kind: &'a str,
+    percentage: f32,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct UpsSummary<'a> {
+    path: &'a str,
+    state: String,
+    percentage: f32,
+    time_to_empty_secs: Option<u64>,
+}
+
 #[derive(Debug)]
 pub struct State {
     prefix: String,
+    show_time: bool,
+    show_power: bool,
+    show_health: bool,
+    show_batteries: bool,
+    show_peripherals: bool,
+    summary_file: Option<PathBuf>,
+
+    /// Online of each line power source, by path, since there can be
+    /// several, like a barrel jack and USB-C ports.
+    line_power: HashMap<String, bool>,
+
+    /// Whether any of them is online.
     plugged_in: bool,
+    plugged_in_known: bool,
+    plugged_in_changed: bool,
     batteries: HashMap<String, msg::Battery>, // TODO Try &str
-    alerts_init: Vec<u64>,
-    alerts_curr: Vec<u64>,
+    rate: Option<f32>,                        // W, smoothed.
+    peripherals: HashMap<String, msg::Peripheral>,
+    ups: HashMap<String, msg::Ups>,
+    mains_failed: bool,
+    alert_triggers: alert::Triggers,
+    alert_time_left: Option<Duration>,
+    alert_time_left_armed: bool,
+    alert_health: Option<u64>,
+    alert_health_sent: bool,
+    alert_plug: bool,
+    alert_full: bool,
+    alert_full_pending: bool,
+    alert_not_charging: Option<Duration>,
+    not_charging_since: Option<Instant>,
+    not_charging_sent: bool,
+    alert_peripheral: Option<u64>,
+    alert_peripheral_sent: HashSet<String>,
+    critical: Option<critical::Action>,
     prev_dir: Direction,
 }
 
 impl State {
-    pub fn new(prefix: &str, alert_triggers: &[u64]) -> Result<Self> {
-        match alert_triggers.iter().find(|n| **n > 100) {
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let alert_triggers = &settings.alert_triggers;
+        match alert_triggers
+            .iter()
+            .map(|t| t.threshold)
+            .chain(settings.critical.as_ref().map(|c| c.threshold))
+            .find(|n| *n > 100)
+        {
             Some(n) => {
                 Err(anyhow!("Alert value out of percentage range: {:?}", n))
             }
             None => Ok(Self {
-                prefix: prefix.to_owned(),
+                prefix: settings.prefix.to_owned(),
+                show_time: settings.show_time,
+                show_power: settings.show_power,
+                show_health: settings.show_health,
+                show_batteries: settings.show_batteries,
+                show_peripherals: settings.show_peripherals,
+                summary_file: settings.summary_file.clone(),
+                line_power: HashMap::new(),
                 plugged_in: false,
+                plugged_in_known: false,
+                plugged_in_changed: false,
                 batteries: HashMap::new(),
-                alerts_init: alert_triggers.to_vec(),
-                alerts_curr: alert_triggers.to_vec(),
+                rate: None,
+                peripherals: HashMap::new(),
+                ups: HashMap::new(),
+                mains_failed: false,
+                alert_triggers: alert::Triggers::new(
+                    alert_triggers,
+                    alert::Direction::Falling,
+                    None,
+                ),
+                alert_time_left: settings.alert_time_left,
+                alert_time_left_armed: true,
+                alert_health: settings.alert_health,
+                alert_health_sent: false,
+                alert_plug: settings.alert_plug,
+                alert_full: settings.alert_full,
+                alert_full_pending: false,
+                alert_not_charging: settings.alert_not_charging,
+                not_charging_since: None,
+                not_charging_sent: false,
+                alert_peripheral: settings.alert_peripheral,
+                alert_peripheral_sent: HashSet::new(),
+                critical: settings
+                    .critical
+                    .clone()
+                    .map(critical::Action::new),
                 prev_dir: Direction::Dec,
             }),
         }
@@ -60,9 +191,190 @@ impl State {
         self.prev_dir = curr_dir;
 
         if let (Dec, Inc | Full | Unknown) = (curr_dir, prev_dir) {
-            self.alerts_curr = self.alerts_init.clone();
-            tracing::debug!("Alerts reset: {:?}", &self.alerts_curr[..]);
+            self.alert_triggers.reset();
+            self.alert_time_left_armed = true;
+            tracing::debug!("Alerts reset.");
+        }
+
+        let mut alerts = Vec::new();
+        alerts.extend(self.alert_mains());
+        alerts.extend(self.alert_percentage(curr_dir));
+        if curr_dir == Dec {
+            alerts.extend(self.alert_time_left());
+        }
+        alerts.extend(self.alert_health());
+        alerts.extend(self.alert_plug());
+        alerts.extend(self.alert_full());
+        alerts.extend(self.alert_not_charging());
+        alerts.extend(self.alert_peripherals());
+        alerts.extend(self.critical_action(curr_dir));
+        (!alerts.is_empty()).then_some(alerts)
+    }
+
+    fn alert_time_left(&mut self) -> Option<Alert> {
+        let threshold = self.alert_time_left?;
+        let time_left = self.time_left()?;
+        if self.alert_time_left_armed && time_left < threshold {
+            self.alert_time_left_armed = false;
+            let summary = format!(
+                "Battery time left below {} minutes!",
+                threshold.as_secs() / 60
+            );
+            let body = fmt_time(time_left);
+            Some(Alert::new(alert::Level::Hi, &summary, &body))
+        } else {
+            None
         }
+    }
+
+    fn critical_action(&mut self, curr_dir: Direction) -> Option<Alert> {
+        let pct = self.percentage();
+        let plugged_in = self.plugged_in;
+        let action = self.critical.as_mut()?;
+        // Not plugged-in, but still not discharging, is where a UPS is
+        // back on mains.
+        if plugged_in || curr_dir != Direction::Dec {
+            action.cancel();
+            None
+        } else {
+            match pct {
+                Some(pct)
+                    if action.is_armed() && pct < action.threshold() =>
+                {
+                    Some(action.trigger(pct))
+                }
+                _ => None,
+            }
+        }
+    }
+
+    /// Health only degrades slowly, so once is enough.
+    fn alert_health(&mut self) -> Option<Alert> {
+        let threshold = self.alert_health?;
+        let health = self.health()?;
+        if !self.alert_health_sent && health < threshold as f32 {
+            self.alert_health_sent = true;
+            let summary = format!("Battery health below {}%!", threshold);
+            let body = format!(
+                "Battery holds only {:.0}% of its design capacity.",
+                health
+            );
+            Some(Alert::new(alert::Level::Mid, &summary, &body))
+        } else {
+            None
+        }
+    }
+
+    fn alert_mains(&mut self) -> Option<Alert> {
+        let failed = self.is_mains_failed();
+        if failed == self.mains_failed {
+            return None;
+        }
+        self.mains_failed = failed;
+        let body = self.ups_details();
+        let alert = if failed {
+            Alert::new(alert::Level::Hi, "Mains power failed!", &body)
+        } else {
+            Alert::new(alert::Level::Lo, "Mains power restored.", &body)
+        };
+        Some(alert)
+    }
+
+    /// Any UPS running on its battery means the mains are out.
+    fn is_mains_failed(&self) -> bool {
+        self.ups
+            .values()
+            .any(|u| u.state == msg::BatteryState::Discharging)
+    }
+
+    fn ups_details(&self) -> String {
+        let mut ups: Vec<&msg::Ups> = self.ups.values().collect();
+        ups.sort_by_key(|u| &u.path);
+        ups.iter()
+            .map(|u| {
+                let time = u.time_to_empty.map_or_else(String::new, |t| {
+                    format!(", {} left", fmt_time(t))
+                });
+                format!("{}: {:.0}%{}", u.path, u.percentage, time)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn alert_plug(&mut self) -> Option<Alert> {
+        let changed = std::mem::take(&mut self.plugged_in_changed);
+        if !(self.alert_plug && changed) {
+            return None;
+        }
+        let summary = if self.plugged_in {
+            "Power plugged-in."
+        } else {
+            "Power unplugged."
+        };
+        let body = self
+            .percentage()
+            .map_or_else(String::new, |pct| format!("{}%", pct));
+        Some(Alert::new(alert::Level::Lo, summary, &body))
+    }
+
+    fn alert_full(&mut self) -> Option<Alert> {
+        let pending = std::mem::take(&mut self.alert_full_pending);
+        (self.alert_full && pending).then(|| {
+            Alert::new(alert::Level::Lo, "Battery fully charged.", "")
+        })
+    }
+
+    /// Batteries tend to report their new state a little after the line
+    /// power does, so the condition has to persist for a while, before it
+    /// is worth alerting about. Checked only on updates, so the alert comes
+    /// with the first one after the grace period, which, while discharging,
+    /// is typically within a couple of minutes, as the percentage drops.
+    fn alert_not_charging(&mut self) -> Option<Alert> {
+        let grace = self.alert_not_charging?;
+        if !self.is_discharging_while_plugged_in() {
+            self.not_charging_since = None;
+            self.not_charging_sent = false;
+            return None;
+        }
+        let since = *self.not_charging_since.get_or_insert_with(Instant::now);
+        if self.not_charging_sent || since.elapsed() < grace {
+            return None;
+        }
+        self.not_charging_sent = true;
+        let summary = "Plugged-in, but battery is discharging!";
+        let body = "Charger may be too weak or faulty.";
+        Some(Alert::new(alert::Level::Mid, summary, body))
+    }
+
+    /// Once per device, until it is charged back above the threshold.
+    fn alert_peripherals(&mut self) -> Vec<Alert> {
+        let Some(threshold) = self.alert_peripheral else {
+            return Vec::new();
+        };
+        let mut alerts = Vec::new();
+        for p in self.peripherals.values() {
+            if p.percentage >= threshold as f32 {
+                self.alert_peripheral_sent.remove(&p.path);
+            } else if self.alert_peripheral_sent.insert(p.path.clone()) {
+                let summary =
+                    format!("{} battery below {}%!", p.kind, threshold);
+                let body = format!("{}: {:.0}%", p.path, p.percentage);
+                alerts.push(Alert::new(alert::Level::Mid, &summary, &body));
+            }
+        }
+        alerts
+    }
+
+    fn is_discharging_while_plugged_in(&self) -> bool {
+        self.plugged_in
+            && self
+                .batteries
+                .values()
+                .any(|b| b.state == msg::BatteryState::Discharging)
+    }
+
+    fn alert_percentage(&mut self, curr_dir: Direction) -> Option<Alert> {
+        use Direction::*;
 
         match (curr_dir, self.percentage()) {
             (Dec, None) => {
@@ -71,51 +383,37 @@ impl State {
                      current power level is unknown!";
                 let body = "";
                 let alert = Alert::new(alert::Level::Hi, summary, body);
-                Some(vec![alert])
+                Some(alert)
             }
             (Dec, Some(pct)) => {
-                let (mut triggered, remaining): (Vec<u64>, Vec<u64>) = self
-                    .alerts_curr
-                    .iter()
-                    .partition(|threshold| threshold > &&pct);
-                self.alerts_curr = remaining;
-                triggered.sort();
-                tracing::debug!(
-                    "Alerts remaining: {:?}",
-                    &self.alerts_curr[..]
-                );
-                if let Some(threshold) = triggered.first() {
-                    let level = match () {
-                        // TODO User-specifyable alert urgency levels.
-                        _ if *threshold <= 25 => alert::Level::Hi,
-                        _ if *threshold <= 50 => alert::Level::Mid,
-                        _ if *threshold <= 100 => alert::Level::Lo,
-                        _ => unreachable!(
-                            "Threshold value out of range: {:?}",
-                            threshold
-                        ),
-                    };
-                    let summary =
-                        format!("Battery power bellow {}%!", *threshold);
-                    let body = format!("{}%", pct);
-                    let alert = Alert::new(level, &summary, &body);
-                    Some(vec![alert])
-                } else {
-                    None
-                }
+                let trigger = self.alert_triggers.check(pct)?;
+                let summary =
+                    format!("Battery power bellow {}%!", trigger.threshold);
+                let body = format!("{}%", pct);
+                let alert = Alert::new(level(&trigger), &summary, &body);
+                Some(alert)
             }
             _ => None,
         }
     }
 
     fn direction(&self) -> Direction {
-        if !self.plugged_in {
+        if self.batteries.is_empty() && !self.ups.is_empty() {
+            self.ups_direction()
+        } else if !self.plugged_in {
             tracing::debug!("Direction::Decreasing because not plugged-in.");
             Direction::Dec
         } else {
             tracing::debug!("Batteries: {:?}", self.batteries);
+            // Stopped at the charge limit is as full as it is going to get.
             let states: HashSet<msg::BatteryState> =
-                HashSet::from_iter(self.batteries.values().map(|b| b.state));
+                HashSet::from_iter(self.batteries.values().map(|b| {
+                    if b.is_held_at_limit() {
+                        msg::BatteryState::FullyCharged
+                    } else {
+                        b.state
+                    }
+                }));
             if states.is_empty() {
                 tracing::warn!(
                     "Direction::Unknown because plugged-in, but \
@@ -124,7 +422,6 @@ impl State {
                 );
                 Direction::Unknown
             } else if states.contains(&msg::BatteryState::Discharging) {
-                // TODO Should this be some sort of an alert?
                 tracing::warn!(
                     "Direction::Decreasing because plugged-in, but \
                     battery states contain Discharging: {:?}",
@@ -169,12 +466,202 @@ impl State {
         }
     }
 
+    /// Without batteries of our own, we are only as powered as our UPSes.
+    fn ups_direction(&self) -> Direction {
+        let states: HashSet<msg::BatteryState> =
+            HashSet::from_iter(self.ups.values().map(|u| u.state));
+        tracing::debug!("UPS states: {:?}", states);
+        if states.contains(&msg::BatteryState::Discharging) {
+            Direction::Dec
+        } else if states.contains(&msg::BatteryState::Charging) {
+            Direction::Inc
+        } else if states.iter().all(|s| *s == msg::BatteryState::FullyCharged)
+        {
+            Direction::Full
+        } else {
+            Direction::Unknown
+        }
+    }
+
+    /// Prefer what the battery itself estimates, but with multiple
+    /// batteries we need the combined estimate, from the combined rate.
+    fn time_left(&self) -> Option<Duration> {
+        if self.batteries.is_empty() {
+            return match self.direction() {
+                Direction::Dec => {
+                    self.ups.values().filter_map(|u| u.time_to_empty).min()
+                }
+                _ => None,
+            };
+        }
+        let batteries: Vec<&msg::Battery> = self.batteries.values().collect();
+        let (reported, energy): (Option<Duration>, f32) =
+            match self.direction() {
+                Direction::Dec => (
+                    batteries.first().and_then(|b| b.time_to_empty),
+                    batteries.iter().map(|b| b.energy).sum(),
+                ),
+                Direction::Inc => (
+                    batteries.first().and_then(|b| b.time_to_full),
+                    batteries.iter().map(|b| b.energy_full - b.energy).sum(),
+                ),
+                Direction::Full | Direction::Unknown => return None,
+            };
+        match (&batteries[..], reported) {
+            ([_], Some(time_left)) => Some(time_left),
+            _ => {
+                let rate = self.rate.filter(|rate| *rate > 0.0)?;
+                Duration::try_from_secs_f32(energy / rate * 3600.0).ok()
+            }
+        }
+    }
+
+    /// Current power draw (or charge) in W.
+    fn power(&self) -> Option<f32> {
+        let rates: Vec<f32> = self
+            .batteries
+            .values()
+            .filter_map(|b| b.energy_rate)
+            .collect();
+        (!rates.is_empty()).then(|| rates.iter().sum())
+    }
+
+    /// Percentage of the combined design capacity which is still left.
+    fn health(&self) -> Option<f32> {
+        let designs: Option<Vec<f32>> = self
+            .batteries
+            .values()
+            .map(|b| b.energy_full_design.filter(|design| *design > 0.0))
+            .collect();
+        match designs {
+            Some(designs) if !designs.is_empty() => {
+                let full: f32 =
+                    self.batteries.values().map(|b| b.energy_full).sum();
+                let design: f32 = designs.iter().sum();
+                Some(full / design * 100.0)
+            }
+            _ => {
+                let healths: Vec<f32> = self
+                    .batteries
+                    .values()
+                    .filter_map(msg::Battery::health)
+                    .collect();
+                #[allow(clippy::cast_precision_loss)]
+                let n = healths.len() as f32;
+                (!healths.is_empty()).then(|| healths.iter().sum::<f32>() / n)
+            }
+        }
+    }
+
+    fn summary(&self) -> Summary<'_> {
+        let mut batteries: Vec<BatterySummary> = self
+            .batteries
+            .values()
+            .map(|b| BatterySummary {
+                path: &b.path,
+                state: format!("{:?}", b.state),
+                percentage: b.percentage(),
+                energy_wh: b.energy,
+                energy_full_wh: b.energy_full,
+                energy_full_design_wh: b.energy_full_design,
+                energy_rate_w: b.energy_rate,
+                voltage_v: b.voltage,
+                health_pct: b.health(),
+                charge_start_threshold: b
+                    .charge_limit
+                    .and_then(|limit| limit.start),
+                charge_end_threshold: b.charge_limit.map(|limit| limit.end),
+            })
+            .collect();
+        batteries.sort_by_key(|b| b.path);
+        let mut peripherals: Vec<PeripheralSummary> = self
+            .peripherals
+            .values()
+            .map(|p| PeripheralSummary {
+                path: &p.path,
+                kind: &p.kind,
+                percentage: p.percentage,
+            })
+            .collect();
+        peripherals.sort_by_key(|p| p.path);
+        let mut ups: Vec<UpsSummary> = self
+            .ups
+            .values()
+            .map(|u| UpsSummary {
+                path: &u.path,
+                state: format!("{:?}", u.state),
+                percentage: u.percentage,
+                time_to_empty_secs: u.time_to_empty.map(|t| t.as_secs()),
+            })
+            .collect();
+        ups.sort_by_key(|u| u.path);
+        Summary {
+            direction: self.direction().to_char(),
+            plugged_in: self.plugged_in,
+            mains_failed: self.is_mains_failed(),
+            percentage: self.percentage(),
+            time_left_secs: self.time_left().map(|t| t.as_secs()),
+            power_w: self.power(),
+            health_pct: self.health(),
+            batteries,
+            peripherals,
+            ups,
+        }
+    }
+
+    fn update_plugged_in(&mut self) {
+        let online = self.line_power.values().any(|online| *online);
+        // Not alerting about what was already the case at startup.
+        self.plugged_in_changed |=
+            self.plugged_in_known && online != self.plugged_in;
+        self.plugged_in_known = true;
+        self.plugged_in = online;
+    }
+
+    /// Failure is only logged, since the status line itself is fine.
+    fn write_summary(&self) {
+        let Some(path) = &self.summary_file else {
+            return;
+        };
+        if let Err(error) = serde_json::to_string(&self.summary())
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(path, json)?))
+        {
+            tracing::error!(?path, ?error, "Failed to write summary.");
+        }
+    }
+
+    fn update_rate(&mut self) {
+        let rate = self.power().unwrap_or(0.0);
+        // Zero is also what is reported when the rate is not yet known.
+        if rate > 0.0 {
+            self.rate = Some(match self.rate {
+                None => rate,
+                Some(prev) => prev + RATE_SMOOTHING * (rate - prev),
+            });
+        }
+        tracing::debug!(rate = ?self.rate, "Energy rate updated.");
+    }
+
+    /// Lowest charge limit (AKA conservation mode) among the batteries.
+    fn charge_limit(&self) -> Option<f32> {
+        self.batteries
+            .values()
+            .filter_map(|b| b.charge_limit.map(|limit| limit.end))
+            .reduce(f32::min)
+    }
+
     fn percentage(&self) -> Option<u64> {
-        (!self.batteries.is_empty()).then_some(()).and_then(|()| {
+        if self.batteries.is_empty() {
+            // UPSes only report percentages, so the lowest is the safest.
+            let pct =
+                self.ups.values().map(|u| u.percentage).reduce(f32::min)?;
+            crate::math::percentage_floor(pct, 100.0)
+        } else {
             let cur = self.batteries.values().map(|b| b.energy).sum();
             let tot = self.batteries.values().map(|b| b.energy_full).sum();
             crate::math::percentage_floor(cur, tot)
-        })
+        }
     }
 }
 
@@ -182,6 +669,7 @@ impl crate::pipeline::State for State {
     type Event = msg::Msg;
 
     fn update(&mut self, msg: Self::Event) -> Result<Option<Vec<Alert>>> {
+        let mut rate_sampled = false;
         match msg {
             msg::Msg::Battery(b) if b.path.ends_with("/DisplayDevice") => {
                 tracing::warn!(
@@ -190,21 +678,100 @@ impl crate::pipeline::State for State {
                 );
             }
             msg::Msg::Battery(b) => {
-                self.batteries.insert(b.path.clone(), b);
+                let full = b.state == msg::BatteryState::FullyCharged;
+                if let Some(prev) = self.batteries.insert(b.path.clone(), b) {
+                    self.alert_full_pending |=
+                        full && prev.state != msg::BatteryState::FullyCharged;
+                }
+                rate_sampled = true;
+            }
+            msg::Msg::Ups(u) => {
+                self.ups.insert(u.path.clone(), u);
             }
-            msg::Msg::LinePower(msg::LinePower { online, .. }) => {
-                self.plugged_in = online;
+            msg::Msg::Peripheral(p) => {
+                self.peripherals.insert(p.path.clone(), p);
             }
+            msg::Msg::LinePower(msg::LinePower { path, online }) => {
+                self.line_power.insert(path, online);
+                self.update_plugged_in();
+            }
+            msg::Msg::Removed(path) => {
+                tracing::info!(?path, "Device removed.");
+                if self.batteries.remove(&path).is_some() {
+                    // Averaged over what is no longer there.
+                    self.rate = None;
+                    rate_sampled = true;
+                }
+                self.peripherals.remove(&path);
+                self.alert_peripheral_sent.remove(&path);
+                self.ups.remove(&path);
+                if self.line_power.remove(&path).is_some() {
+                    self.update_plugged_in();
+                }
+            }
+        }
+        if self.direction() != self.prev_dir {
+            // Charging rate has nothing to do with the discharging one.
+            self.rate = None;
         }
+        if rate_sampled {
+            self.update_rate();
+        }
+        self.write_summary();
         Ok(self.alerts())
     }
 
     fn display<W: std::io::Write>(&mut self, mut buf: W) -> Result<()> {
-        write!(buf, "{}{}", &self.prefix, self.direction().to_char())?;
+        let symbol = if self.is_mains_failed() {
+            '!'
+        } else {
+            self.direction().to_char()
+        };
+        write!(buf, "{}{}", &self.prefix, symbol)?;
         match self.percentage() {
             None => write!(buf, "---%")?,
             Some(pct) => write!(buf, "{:3.0}%", pct)?,
         }
+        if let Some(limit) = self.charge_limit() {
+            write!(buf, " ≤{:2.0}%", limit)?;
+        }
+        if self.show_batteries {
+            let mut batteries: Vec<&msg::Battery> =
+                self.batteries.values().collect();
+            batteries.sort_by_key(|b| &b.path);
+            for b in batteries {
+                match b.percentage() {
+                    None => write!(buf, " {} ---%", b.path)?,
+                    Some(pct) => write!(buf, " {} {:3.0}%", b.path, pct)?,
+                }
+            }
+        }
+        if self.show_time {
+            match self.time_left() {
+                None => write!(buf, " -:--")?,
+                Some(time_left) => write!(buf, " {}", fmt_time(time_left))?,
+            }
+        }
+        if self.show_power {
+            match self.power() {
+                None => write!(buf, " --.-W")?,
+                Some(watts) => write!(buf, " {:4.1}W", watts)?,
+            }
+        }
+        if self.show_health {
+            match self.health() {
+                None => write!(buf, " ♥---%")?,
+                Some(pct) => write!(buf, " ♥{:3.0}%", pct)?,
+            }
+        }
+        if self.show_peripherals {
+            let mut peripherals: Vec<&msg::Peripheral> =
+                self.peripherals.values().collect();
+            peripherals.sort_by_key(|p| &p.path);
+            for p in peripherals {
+                write!(buf, " {} {:3.0}%", p.kind, p.percentage)?;
+            }
+        }
         writeln!(buf)?;
         Ok(())
     }
@@ -0,0 +1,314 @@
+// Device updates straight from the kernel, for systems without upowerd.
+//
+// Ref: <https://www.kernel.org/doc/html/latest/power/power_supply_class.html>
+// Ref: <https://www.kernel.org/doc/Documentation/ABI/testing/sysfs-class-power>
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::msg;
+
+pub const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+fn read_attr(dir: &Path, name: &str) -> Result<Option<String>> {
+    let path = dir.join(name);
+    match std::fs::read_to_string(&path) {
+        Ok(data) => Ok(Some(data.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read {:?}: {:?}", path, e)),
+    }
+}
+
+fn read_num(dir: &Path, name: &str) -> Result<Option<f32>> {
+    read_attr(dir, name)?
+        .map(|data| {
+            data.parse::<f32>()
+                .context(format!("attribute: {:?}, data: {:?}", name, data))
+        })
+        .transpose()
+}
+
+fn battery_state(status: &str) -> msg::BatteryState {
+    match status {
+        "Charging" => msg::BatteryState::Charging,
+        "Discharging" => msg::BatteryState::Discharging,
+        "Full" => msg::BatteryState::FullyCharged,
+        // Plugged-in, but held back, same as upower reports it.
+        "Not charging" => msg::BatteryState::PendingCharge,
+        s => {
+            tracing::warn!("unexpected battery state: {:?}", s);
+            msg::BatteryState::Unexpected
+        }
+    }
+}
+
+/// Energy now, full and full by design, in Wh.
+fn read_energy(dir: &Path) -> Result<(f32, f32, Option<f32>)> {
+    match (read_num(dir, "energy_now")?, read_num(dir, "energy_full")?) {
+        // Reported in µWh:
+        (Some(now), Some(full)) => {
+            let design = read_num(dir, "energy_full_design")?;
+            Ok((now / 1e6, full / 1e6, design.map(|uwh| uwh / 1e6)))
+        }
+        _ => {
+            // Reported in µAh, which we need to convert using µV:
+            let now = read_num(dir, "charge_now")?.ok_or_else(|| {
+                anyhow!("missing energy_now and charge_now")
+            })?;
+            let full = read_num(dir, "charge_full")?.ok_or_else(|| {
+                anyhow!("missing energy_full and charge_full")
+            })?;
+            let voltage = match read_num(dir, "voltage_min_design")? {
+                Some(v) => v,
+                None => read_num(dir, "voltage_now")?.ok_or_else(|| {
+                    anyhow!("missing voltage to convert charge to energy")
+                })?,
+            };
+            let wh = |uah: f32| (uah / 1e6) * (voltage / 1e6);
+            let design = read_num(dir, "charge_full_design")?;
+            Ok((wh(now), wh(full), design.map(wh)))
+        }
+    }
+}
+
+/// Power, in W.
+fn read_rate(dir: &Path) -> Result<Option<f32>> {
+    // Reported in µW, or µA which we need to convert using µV:
+    match read_num(dir, "power_now")? {
+        Some(uw) => Ok(Some(uw / 1e6)),
+        None => match (
+            read_num(dir, "current_now")?,
+            read_num(dir, "voltage_now")?,
+        ) {
+            (Some(ua), Some(uv)) => Ok(Some((ua / 1e6) * (uv / 1e6))),
+            _ => Ok(None),
+        },
+    }
+}
+
+/// Many peripherals only report a coarse level. Approximated the same way
+/// upowerd does it.
+fn read_percentage(dir: &Path) -> Result<Option<f32>> {
+    match read_num(dir, "capacity")? {
+        Some(pct) => Ok(Some(pct)),
+        None => {
+            let pct = match read_attr(dir, "capacity_level")?.as_deref() {
+                Some("Critical") => Some(5.0),
+                Some("Low") => Some(10.0),
+                Some("Normal") => Some(55.0),
+                Some("High") => Some(70.0),
+                Some("Full") => Some(100.0),
+                _ => None,
+            };
+            Ok(pct)
+        }
+    }
+}
+
+fn read_charge_limit(dir: &Path) -> Result<Option<msg::ChargeLimit>> {
+    // Older ThinkPad drivers use the "stop" and unprefixed names.
+    let start = match read_num(dir, "charge_control_start_threshold")? {
+        Some(pct) => Some(pct),
+        None => read_num(dir, "charge_start_threshold")?,
+    };
+    let end = match read_num(dir, "charge_control_end_threshold")? {
+        Some(pct) => Some(pct),
+        None => read_num(dir, "charge_stop_threshold")?,
+    };
+    Ok(msg::ChargeLimit::new(start, end))
+}
+
+fn read_seconds(dir: &Path, name: &str) -> Result<Option<Duration>> {
+    Ok(read_num(dir, name)?
+        .filter(|secs| *secs > 0.0)
+        .and_then(|secs| Duration::try_from_secs_f32(secs).ok()))
+}
+
+/// Device path, as we report it: just the name of its directory, like
+/// "BAT0".
+fn name(dir: &Path) -> Result<String> {
+    Ok(dir
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid device directory: {:?}", dir))?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Kind of peripheral, as far as the model name tells, since sysfs does not
+/// expose it otherwise. What is not recognized is labeled generically, as
+/// "device".
+fn read_peripheral_kind(dir: &Path) -> Result<String> {
+    const KEYWORDS: [(&str, &str); 10] = [
+        ("mouse", "mouse"),
+        ("keyboard", "keyboard"),
+        ("touchpad", "touchpad"),
+        ("trackpad", "touchpad"),
+        ("headset", "headset"),
+        ("headphone", "headphones"),
+        ("speaker", "speakers"),
+        ("stylus", "pen"),
+        ("controller", "gaming-input"),
+        ("gamepad", "gaming-input"),
+    ];
+    let model = read_attr(dir, "model_name")?
+        .unwrap_or_default()
+        .to_lowercase();
+    let kind = KEYWORDS
+        .iter()
+        .find(|(keyword, _)| model.contains(keyword))
+        .map_or("device", |(_, kind)| kind);
+    Ok(kind.to_string())
+}
+
+fn read(dir: &Path) -> Result<Option<msg::Msg>> {
+    let path = name(dir)?;
+    let msg = match read_attr(dir, "type")?.as_deref() {
+        // USB ones are chargers too, and all there is on some laptops.
+        Some("Mains" | "USB" | "USB_C" | "USB_PD") => {
+            let online = read_attr(dir, "online")?.as_deref() == Some("1");
+            Some(msg::Msg::LinePower(msg::LinePower { path, online }))
+        }
+        // Peripherals (mice, etc) are scoped to "Device", rather than
+        // "System", and are not powering us.
+        Some("Battery")
+            if read_attr(dir, "scope")?.as_deref() == Some("Device") =>
+        {
+            match read_percentage(dir)? {
+                None => None,
+                Some(percentage) => {
+                    Some(msg::Msg::Peripheral(msg::Peripheral {
+                        path,
+                        kind: read_peripheral_kind(dir)?,
+                        percentage,
+                    }))
+                }
+            }
+        }
+        Some("UPS") => {
+            let status = read_attr(dir, "status")?
+                .ok_or_else(|| anyhow!("missing status"))?;
+            match read_percentage(dir)? {
+                None => None,
+                Some(percentage) => Some(msg::Msg::Ups(msg::Ups {
+                    path,
+                    state: battery_state(&status),
+                    percentage,
+                    time_to_empty: read_seconds(dir, "time_to_empty_now")?,
+                })),
+            }
+        }
+        Some("Battery") => {
+            let status = read_attr(dir, "status")?
+                .ok_or_else(|| anyhow!("missing status"))?;
+            let (energy, energy_full, energy_full_design) = read_energy(dir)?;
+            if energy > energy_full {
+                // Some firmware briefly reports slightly more than full.
+                tracing::debug!(?path, energy, energy_full, "Clamping.");
+            }
+            let battery = msg::Battery {
+                path,
+                state: battery_state(&status),
+                energy: energy.min(energy_full),
+                energy_full,
+                energy_full_design,
+                energy_rate: read_rate(dir)?,
+                voltage: read_num(dir, "voltage_now")?.map(|uv| uv / 1e6),
+                // Not to be confused with the "capacity" attribute, which is
+                // the charge percentage. We derive ours from design instead.
+                capacity: None,
+                // Only few drivers provide these:
+                time_to_empty: read_seconds(dir, "time_to_empty_now")?,
+                time_to_full: read_seconds(dir, "time_to_full_now")?,
+                charge_limit: read_charge_limit(dir)?,
+            };
+            battery.check()?;
+            Some(msg::Msg::Battery(battery))
+        }
+        _ => None,
+    };
+    Ok(msg)
+}
+
+struct Supplies {
+    dir: PathBuf,
+    prev: HashMap<PathBuf, msg::Msg>,
+}
+
+impl Supplies {
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            prev: HashMap::new(),
+        }
+    }
+
+    fn devices(&self) -> Result<Vec<PathBuf>> {
+        let mut devices = std::fs::read_dir(&self.dir)?
+            .map(|entry_result| entry_result.map(|entry| entry.path()))
+            .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+        devices.sort();
+        Ok(devices)
+    }
+
+    /// Messages for the devices which changed since the previous read,
+    /// preceded by removals of those which are gone.
+    fn read_changed(&mut self) -> Result<Vec<msg::Msg>> {
+        let devices = self.devices()?;
+        let mut changed = Vec::new();
+        let mut gone: Vec<PathBuf> = self
+            .prev
+            .keys()
+            .filter(|dir| !devices.contains(dir))
+            .cloned()
+            .collect();
+        gone.sort();
+        for dir in gone {
+            self.prev.remove(&dir);
+            changed.push(msg::Msg::Removed(name(&dir)?));
+        }
+        for dir in devices {
+            match read(&dir) {
+                Err(error) => {
+                    tracing::error!(?dir, ?error, "Failed to read device.");
+                }
+                Ok(None) => {}
+                Ok(Some(msg)) => {
+                    if self.prev.get(&dir) != Some(&msg) {
+                        self.prev.insert(dir, msg.clone());
+                        changed.push(msg);
+                    }
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+pub fn messages(
+    dir: &Path,
+    poll_interval: Duration,
+) -> Result<impl Iterator<Item = msg::Msg>> {
+    let mut supplies = Supplies::new(dir);
+    let paths: Vec<PathBuf> = std::iter::once(dir.to_path_buf())
+        .chain(supplies.devices()?)
+        .collect();
+    let watcher = crate::watch::Watcher::new(&paths, Some(poll_interval))?;
+    let messages = watcher.flat_map(move |crate::clock::Tick| match supplies
+        .read_changed()
+    {
+        Err(error) => {
+            tracing::error!(?error, "Failed to read power supplies.");
+            Vec::new()
+        }
+        Ok(msgs) => msgs,
+    });
+    Ok(messages)
+}
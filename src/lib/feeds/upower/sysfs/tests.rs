@@ -0,0 +1,119 @@
+use std::{path::Path, time::Duration};
+
+use crate::test_util::TempDir;
+
+use super::super::msg;
+
+const FIXTURE: &str = "tests/sys-class-power_supply";
+
+fn bat0(state: msg::BatteryState) -> msg::Msg {
+    msg::Msg::Battery(msg::Battery {
+        path: "BAT0".to_string(),
+        state,
+        energy: 43.1,
+        energy_full: 50.06,
+        energy_full_design: Some(57.02),
+        energy_rate: Some(9.8),
+        voltage: Some(12.45),
+        capacity: None,
+        time_to_empty: Some(Duration::from_secs(15840)),
+        time_to_full: None,
+        charge_limit: Some(msg::ChargeLimit {
+            start: Some(75.0),
+            end: 80.0,
+        }),
+    })
+}
+
+#[test]
+fn read_all() {
+    let mut supplies = super::Supplies::new(Path::new(FIXTURE));
+    assert_eq!(
+        vec![
+            msg::Msg::LinePower(msg::LinePower {
+                path: "AC".to_string(),
+                online: true,
+            }),
+            bat0(msg::BatteryState::Discharging),
+            msg::Msg::Battery(msg::Battery {
+                path: "BAT1".to_string(),
+                state: msg::BatteryState::PendingCharge,
+                energy: 22.2,
+                energy_full: 44.4,
+                energy_full_design: Some(88.8),
+                energy_rate: Some(5.9),
+                voltage: Some(11.8),
+                capacity: None,
+                time_to_empty: None,
+                time_to_full: None,
+                charge_limit: None,
+            }),
+            msg::Msg::Peripheral(msg::Peripheral {
+                path: "hidpp_battery_0".to_string(),
+                kind: "mouse".to_string(),
+                percentage: 55.0,
+            }),
+            // USB-C, with nothing plugged-in.
+            msg::Msg::LinePower(msg::LinePower {
+                path: "ucsi-source-psy-USBC000-001".to_string(),
+                online: false,
+            }),
+        ],
+        supplies.read_changed().unwrap()
+    );
+    assert_eq!(Vec::<msg::Msg>::new(), supplies.read_changed().unwrap());
+}
+
+#[test]
+fn read_changed() {
+    let dir = TempDir::with_copy("read_changed", Path::new(FIXTURE));
+    let mut supplies = super::Supplies::new(dir.path());
+    assert_eq!(5, supplies.read_changed().unwrap().len());
+
+    std::fs::write(dir.join("BAT0/status"), "Charging\n").unwrap();
+    assert_eq!(
+        vec![bat0(msg::BatteryState::Charging)],
+        supplies.read_changed().unwrap()
+    );
+
+    std::fs::remove_dir_all(dir.join("BAT1")).unwrap();
+    std::fs::write(dir.join("AC/online"), "0\n").unwrap();
+    assert_eq!(
+        vec![
+            msg::Msg::Removed("BAT1".to_string()),
+            msg::Msg::LinePower(msg::LinePower {
+                path: "AC".to_string(),
+                online: false,
+            })
+        ],
+        supplies.read_changed().unwrap()
+    );
+    assert_eq!(Vec::<msg::Msg>::new(), supplies.read_changed().unwrap());
+
+    let usb_c = "ucsi-source-psy-USBC000-001";
+    std::fs::write(dir.join(usb_c).join("online"), "1\n").unwrap();
+    assert_eq!(
+        vec![msg::Msg::LinePower(msg::LinePower {
+            path: usb_c.to_string(),
+            online: true,
+        })],
+        supplies.read_changed().unwrap()
+    );
+}
+
+#[test]
+fn peripheral_kind() {
+    let dir = TempDir::new("peripheral_kind");
+    let kind = |model: Option<&str>| {
+        let _ = std::fs::remove_file(dir.join("model_name"));
+        if let Some(model) = model {
+            std::fs::write(dir.join("model_name"), model).unwrap();
+        }
+        super::read_peripheral_kind(dir.path()).unwrap()
+    };
+    assert_eq!("keyboard", kind(Some("Wireless Keyboard K270\n")));
+    assert_eq!("headphones", kind(Some("WH-1000XM4 Headphones")));
+    assert_eq!("gaming-input", kind(Some("Xbox Wireless Controller")));
+    assert_eq!("device", kind(Some("G Pro")));
+    assert_eq!("device", kind(None));
+}
@@ -1,6 +1,9 @@
-use super::{msg, state};
+use std::time::Duration;
+
+use crate::{alert::AlertTrigger, test_util::TempDir};
+
+use super::{critical, msg, state, Settings};
 
-// TODO Multi-battery tests.
 // TODO Examine state in tests.
 
 #[test]
@@ -20,17 +23,36 @@ fn dump() {
             state: msg::BatteryState::Discharging,
             energy: 87.2898,
             energy_full: 89.148,
+            energy_full_design: Some(95.0076),
+            energy_rate: Some(41.2794),
+            voltage: Some(12.295),
+            capacity: Some(93.8325),
+            time_to_empty: Some(Duration::from_secs(7560)),
+            time_to_full: None,
+            charge_limit: None,
         }),
         msg::Msg::Battery(msg::Battery {
             path: "/org/freedesktop/UPower/devices/DisplayDevice".to_string(),
             state: msg::BatteryState::Discharging,
             energy: 87.2898,
             energy_full: 89.148,
+            energy_full_design: None,
+            energy_rate: Some(41.2794),
+            voltage: None,
+            capacity: None,
+            time_to_empty: Some(Duration::from_secs(7560)),
+            time_to_full: None,
+            charge_limit: None,
         }),
     ];
     assert_eq!(&messages_expected, &messages_produced);
 
-    let mut state = state::State::new("u ", &[]).unwrap();
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        show_time: true,
+        ..Settings::default()
+    })
+    .unwrap();
     let mut buf: Vec<u8> = Vec::new();
     for msg in messages_produced {
         {
@@ -40,7 +62,7 @@ fn dump() {
         }
     }
     assert_eq!(
-        vec!["u <---%", "u < 97%", "u < 97%"],
+        vec!["u <---% -:--", "u < 97% 2:06", "u < 97% 2:06"],
         String::from_utf8(buf)
             .unwrap()
             .lines()
@@ -62,24 +84,52 @@ fn monitor() {
             state: msg::BatteryState::Discharging,
             energy: 42.8868,
             energy_full: 89.148,
+            energy_full_design: Some(95.0076),
+            energy_rate: Some(0.0),
+            voltage: Some(11.571),
+            capacity: Some(93.8325),
+            time_to_empty: None,
+            time_to_full: None,
+            charge_limit: None,
         }),
         msg::Msg::Battery(msg::Battery {
             path: "BAT0".to_string(),
             state: msg::BatteryState::Discharging,
             energy: 42.8868,
             energy_full: 89.148,
+            energy_full_design: Some(95.0076),
+            energy_rate: Some(0.0),
+            voltage: Some(11.571),
+            capacity: Some(93.8325),
+            time_to_empty: None,
+            time_to_full: None,
+            charge_limit: None,
         }),
         msg::Msg::Battery(msg::Battery {
             path: "BAT0".to_string(),
             state: msg::BatteryState::Discharging,
             energy: 42.8868,
             energy_full: 89.148,
+            energy_full_design: Some(95.0076),
+            energy_rate: Some(0.0),
+            voltage: Some(11.571),
+            capacity: Some(93.8325),
+            time_to_empty: None,
+            time_to_full: None,
+            charge_limit: None,
         }),
         msg::Msg::Battery(msg::Battery {
             path: "BAT0".to_string(),
             state: msg::BatteryState::Discharging,
             energy: 42.8868,
             energy_full: 89.148,
+            energy_full_design: Some(95.0076),
+            energy_rate: Some(0.0),
+            voltage: Some(11.571),
+            capacity: Some(93.8325),
+            time_to_empty: None,
+            time_to_full: None,
+            charge_limit: None,
         }),
         msg::Msg::LinePower(msg::LinePower {
             path: "AC".to_string(),
@@ -92,3 +142,424 @@ fn monitor() {
     ];
     assert_eq!(&messages_expected, &messages_produced);
 }
+
+fn battery(state: msg::BatteryState, energy: f32, rate: f32) -> msg::Msg {
+    msg::Msg::Battery(msg::Battery {
+        path: "BAT0".to_string(),
+        state,
+        energy,
+        energy_full: 50.0,
+        energy_full_design: None,
+        energy_rate: Some(rate),
+        voltage: None,
+        capacity: None,
+        time_to_empty: None,
+        time_to_full: None,
+        charge_limit: None,
+    })
+}
+
+fn line_power(online: bool) -> msg::Msg {
+    msg::Msg::LinePower(msg::LinePower {
+        path: "AC".to_string(),
+        online,
+    })
+}
+
+#[test]
+fn time_left() {
+    use crate::pipeline::State;
+    use msg::BatteryState::{Charging, Discharging};
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        show_time: true,
+        alert_time_left: Some(Duration::from_secs(7 * 60 * 60)),
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut alert_counts: Vec<usize> = Vec::new();
+    for msg in [
+        line_power(false),
+        battery(Discharging, 45.0, 5.0),
+        battery(Discharging, 30.0, 5.0),
+        battery(Discharging, 29.0, 5.0),
+        line_power(true),
+        battery(Charging, 30.0, 10.0),
+        line_power(false),
+        battery(Discharging, 30.0, 5.0),
+    ] {
+        let alerts = state.update(msg).unwrap();
+        alert_counts.push(alerts.map(|a| a.len()).unwrap_or(0));
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(vec![1, 0, 1, 0, 0, 0, 0, 1], alert_counts);
+    assert_eq!(
+        vec![
+            "u <---% -:--",
+            "u < 90% 9:00",
+            "u < 60% 6:00",
+            "u < 58% 5:48",
+            "u < 58% 5:48",
+            "u > 60% 2:00",
+            "u < 60% -:--",
+            "u < 60% 6:00",
+        ],
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .collect::<Vec<&str>>()
+    );
+}
+
+#[test]
+fn power_and_health() {
+    use crate::pipeline::State;
+
+    let dir = TempDir::new("upower-summary");
+    let summary_file = dir.join("summary.json");
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        alert_health: Some(95),
+        show_power: true,
+        show_health: true,
+        summary_file: Some(summary_file.clone()),
+        ..Settings::default()
+    })
+    .unwrap();
+    let output: String =
+        std::fs::read_to_string("tests/upower-dump.txt").unwrap();
+    let lines = output.lines().map(|l| l.to_string());
+    let mut buf: Vec<u8> = Vec::new();
+    let mut alert_counts: Vec<usize> = Vec::new();
+    for msg in msg::Messages::from_lines(Box::new(lines)) {
+        let alerts = state.update(msg).unwrap();
+        alert_counts.push(alerts.map(|a| a.len()).unwrap_or(0));
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(vec![1, 1, 0], alert_counts);
+    assert_eq!(
+        vec![
+            "u <---% --.-W ♥---%",
+            "u < 97% 41.3W ♥ 94%",
+            "u < 97% 41.3W ♥ 94%"
+        ],
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .collect::<Vec<&str>>()
+    );
+
+    let summary: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&summary_file).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(Some(97), summary["percentage"].as_u64());
+    assert_eq!(Some(41), summary["power_w"].as_f64().map(|w| w as u64));
+    assert_eq!(Some(93), summary["health_pct"].as_f64().map(|h| h as u64));
+    assert_eq!(Some("BAT0"), summary["batteries"][0]["path"].as_str());
+}
+
+#[test]
+fn alert_level() {
+    use crate::alert::Level;
+
+    let trigger = |threshold, level| AlertTrigger { threshold, level };
+    assert_eq!(Level::Lo, state::level(&trigger(75, None)));
+    assert_eq!(Level::Mid, state::level(&trigger(50, None)));
+    assert_eq!(Level::Hi, state::level(&trigger(25, None)));
+    assert_eq!(Level::Lo, state::level(&trigger(5, Some(Level::Lo))));
+}
+
+#[test]
+fn critical_action() {
+    use std::cell::RefCell;
+
+    use crate::pipeline::State;
+    use msg::BatteryState::Discharging;
+
+    thread_local! {
+        static SCHEDULED: RefCell<Vec<critical::Scheduled>> =
+            const { RefCell::new(Vec::new()) };
+    }
+    let scheduled = || SCHEDULED.with(|s| s.take());
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        critical: Some(critical::Settings {
+            command: "true".to_string(),
+            threshold: 5,
+            grace: Duration::from_secs(60),
+            spawn: |s| {
+                SCHEDULED.with(|scheduled| scheduled.borrow_mut().push(s))
+            },
+        }),
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut update =
+        |msg| state.update(msg).unwrap().map(|a| a.len()).unwrap_or(0);
+
+    assert_eq!(1, update(line_power(false))); // Unknown level.
+    assert_eq!(1, update(battery(Discharging, 2.0, 5.0)));
+    assert_eq!(0, update(line_power(true)));
+    let canceled = scheduled();
+    assert_eq!(1, canceled.len());
+    assert!(canceled[0].is_canceled());
+
+    assert_eq!(1, update(line_power(false)));
+    assert_eq!(0, update(battery(Discharging, 1.9, 5.0)));
+    let pending = scheduled();
+    assert_eq!(1, pending.len());
+    assert!(!pending[0].is_canceled());
+    assert_eq!("true", pending[0].command);
+    assert_eq!(Duration::from_secs(60), pending[0].grace);
+}
+
+#[test]
+fn plug_full_and_not_charging() {
+    use crate::pipeline::State;
+    use msg::BatteryState::{Charging, Discharging, FullyCharged};
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        alert_plug: true,
+        alert_full: true,
+        alert_not_charging: Some(Duration::ZERO),
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut update =
+        |msg| state.update(msg).unwrap().map(|a| a.len()).unwrap_or(0);
+
+    // Already plugged-in at startup - nothing changed.
+    assert_eq!(0, update(line_power(true)));
+    assert_eq!(0, update(battery(Charging, 49.0, 10.0)));
+    assert_eq!(1, update(battery(FullyCharged, 50.0, 0.0)));
+    assert_eq!(0, update(battery(FullyCharged, 50.0, 0.0)));
+    assert_eq!(1, update(line_power(false)));
+    assert_eq!(0, update(battery(Discharging, 49.0, 10.0)));
+    // Plugged-in and, with no grace period, still discharging.
+    assert_eq!(2, update(line_power(true)));
+    assert_eq!(0, update(battery(Discharging, 48.0, 10.0)));
+    assert_eq!(0, update(battery(Charging, 47.0, 10.0)));
+    assert_eq!(1, update(battery(Discharging, 47.0, 10.0)));
+
+    // Moved over from the barrel jack to USB-C, so still plugged-in.
+    const USB_C: &str = "ucsi-source-psy-USBC000-001";
+    let usb_c = msg::Msg::LinePower(msg::LinePower {
+        path: USB_C.to_string(),
+        online: true,
+    });
+    assert_eq!(0, update(usb_c));
+    assert_eq!(0, update(line_power(false)));
+    assert_eq!(1, update(msg::Msg::Removed(USB_C.to_string())));
+}
+
+#[test]
+fn batteries_and_peripherals() {
+    use crate::pipeline::State;
+
+    let output: String =
+        std::fs::read_to_string("tests/upower-dump-peripherals.txt").unwrap();
+    let lines = output.lines().map(|l| l.to_string());
+    const HEADSET: &str = "/org/bluez/hci0/dev_88_C9_E8_0D_3F_21";
+    const KEYBOARD: &str = "/sys/devices/virtual/misc/uhid/\
+        0005:04CA:0069.0001/power_supply/hid-0c:04:ca:00:69:00-battery";
+    let peripherals: Vec<msg::Msg> =
+        msg::Messages::from_lines(Box::new(lines)).collect();
+    let peripheral = |path: &str, kind: &str, percentage| {
+        msg::Msg::Peripheral(msg::Peripheral {
+            path: path.to_string(),
+            kind: kind.to_string(),
+            percentage,
+        })
+    };
+    assert_eq!(
+        vec![
+            peripheral("hidpp_battery_0", "mouse", 55.0),
+            peripheral(HEADSET, "headset", 40.0),
+            peripheral(KEYBOARD, "keyboard", 12.0),
+            // Not the monitor, which has no battery to speak of.
+        ],
+        peripherals
+    );
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        show_batteries: true,
+        show_peripherals: true,
+        alert_peripheral: Some(20),
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut update = |msg| {
+        let alerts = state.update(msg).unwrap().map(|a| a.len());
+        state.display(&mut buf).unwrap();
+        alerts.unwrap_or(0)
+    };
+    assert_eq!(0, update(line_power(true)));
+    assert_eq!(0, update(battery(msg::BatteryState::Charging, 40.0, 9.0)));
+    assert_eq!(
+        0,
+        update(msg::Msg::Battery(msg::Battery {
+            path: "BAT1".to_string(),
+            energy: 10.0,
+            energy_full: 25.0,
+            ..match battery(msg::BatteryState::Charging, 0.0, 1.0) {
+                msg::Msg::Battery(b) => b,
+                _ => unreachable!(),
+            }
+        }))
+    );
+    let mut alerts = 0;
+    for msg in peripherals {
+        alerts += update(msg);
+    }
+    assert_eq!(1, alerts); // keyboard
+    assert_eq!(0, update(peripheral(KEYBOARD, "keyboard", 5.0)));
+    assert_eq!(0, update(peripheral(KEYBOARD, "keyboard", 80.0)));
+    assert_eq!(1, update(peripheral(KEYBOARD, "keyboard", 15.0)));
+    assert_eq!(
+        Some(
+            "u > 66% BAT0  80% BAT1  40% \
+             headset  40% keyboard  15% mouse  55%"
+        ),
+        String::from_utf8(buf.clone()).unwrap().lines().last()
+    );
+
+    buf.clear();
+    let mut update = |msg| {
+        let alerts = state.update(msg).unwrap().map(|a| a.len());
+        state.display(&mut buf).unwrap();
+        alerts.unwrap_or(0)
+    };
+    assert_eq!(0, update(msg::Msg::Removed("BAT1".to_string())));
+    assert_eq!(0, update(msg::Msg::Removed(KEYBOARD.to_string())));
+    assert_eq!(
+        Some("u > 80% BAT0  80% headset  40% mouse  55%"),
+        String::from_utf8(buf).unwrap().lines().last()
+    );
+}
+
+#[test]
+fn ups() {
+    use crate::pipeline::State;
+    use msg::BatteryState::{Charging, Discharging, FullyCharged};
+
+    const UPS: &str = "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4/\
+        1-4:1.0/usbmisc/hiddev0";
+    let ups = |state, percentage, minutes: u64| {
+        msg::Msg::Ups(msg::Ups {
+            path: UPS.to_string(),
+            state,
+            percentage,
+            time_to_empty: Some(Duration::from_secs(minutes * 60)),
+        })
+    };
+    let output: String =
+        std::fs::read_to_string("tests/upower-monitor-ups.txt").unwrap();
+    let lines = output.lines().map(|l| l.to_string());
+    let messages: Vec<msg::Msg> =
+        msg::Messages::from_lines(Box::new(lines)).collect();
+    assert_eq!(
+        vec![
+            ups(FullyCharged, 100.0, 49),
+            ups(Discharging, 100.0, 47),
+            ups(Discharging, 24.0, 9),
+            ups(Charging, 24.0, 9),
+        ],
+        messages
+    );
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        alert_triggers: vec![AlertTrigger::from(25), AlertTrigger::from(10)],
+        alert_time_left: Some(Duration::from_secs(10 * 60)),
+        show_time: true,
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut alerts = Vec::new();
+    for msg in messages {
+        alerts.push(state.update(msg).unwrap().map_or(0, |a| a.len()));
+        state.display(&mut buf).unwrap();
+    }
+    assert_eq!(vec![0, 1, 2, 1], alerts);
+    assert_eq!(
+        vec![
+            "u =100% -:--",
+            "u !100% 0:47",
+            "u ! 24% 0:09",
+            "u > 24% -:--"
+        ],
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .collect::<Vec<&str>>()
+    );
+}
+
+#[test]
+fn charge_limit() {
+    use crate::pipeline::State;
+
+    let output = "\
+Device: /org/freedesktop/UPower/devices/battery_BAT0
+  native-path:          BAT0
+  battery
+    state:               pending-charge
+    energy:              40 Wh
+    energy-full:         50 Wh
+    charge-start-threshold:        75%
+    charge-end-threshold:          80%
+    charge-threshold-enabled:      yes
+    charge-threshold-supported:    yes
+
+";
+    let lines = output.lines().map(|l| l.to_string());
+    let messages: Vec<msg::Msg> =
+        msg::Messages::from_lines(Box::new(lines)).collect();
+    let limited = match &messages[..] {
+        [msg::Msg::Battery(b)] => b.clone(),
+        _ => panic!("Unexpected messages: {:?}", messages),
+    };
+    assert_eq!(
+        Some(msg::ChargeLimit {
+            start: Some(75.0),
+            end: 80.0
+        }),
+        limited.charge_limit
+    );
+    assert!(limited.is_held_at_limit());
+
+    let mut state = state::State::new(&Settings {
+        prefix: "u ".to_string(),
+        alert_triggers: vec![AlertTrigger::from(100)],
+        ..Settings::default()
+    })
+    .unwrap();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut alerts = Vec::new();
+    let unlimited = msg::Battery {
+        charge_limit: None,
+        ..limited.clone()
+    };
+    for b in [limited, unlimited] {
+        for msg in [line_power(true), msg::Msg::Battery(b)] {
+            alerts.push(state.update(msg).unwrap().map_or(0, |a| a.len()));
+            state.display(&mut buf).unwrap();
+        }
+    }
+    assert_eq!(vec![0, 0, 0, 1], alerts);
+    assert_eq!(
+        vec!["u ?---%", "u = 80% ≤80%", "u = 80% ≤80%", "u < 80%"],
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .collect::<Vec<&str>>()
+    );
+}
@@ -5,3 +5,8 @@ pub mod logger;
 pub mod math;
 pub mod pipeline;
 pub mod process;
+pub mod units;
+pub mod watch;
+
+#[cfg(test)]
+mod test_util;
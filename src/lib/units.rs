@@ -0,0 +1,54 @@
+/// Human-readable, in binary (1024-based) units, like "12.3G".
+#[allow(clippy::cast_precision_loss)]
+pub fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Not "1024.0K", once rounded to the one decimal.
+    while value >= 1023.95 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Human-readable, to the two most significant units, like "4d 3h", "3h 2m"
+/// or "2m".
+pub fn fmt_duration(duration: std::time::Duration) -> String {
+    let mins = duration.as_secs() / 60;
+    let (days, hours, mins) = (mins / (24 * 60), mins / 60 % 24, mins % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", mins),
+        (0, _) => format!("{}h {}m", hours, mins),
+        (_, _) => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn t_fmt_bytes() {
+        assert_eq!("0B", super::fmt_bytes(0));
+        assert_eq!("1023B", super::fmt_bytes(1023));
+        assert_eq!("1.0K", super::fmt_bytes(1024));
+        assert_eq!("1.0M", super::fmt_bytes(1024 * 1024 - 1));
+        assert_eq!("1.5M", super::fmt_bytes(3 * 512 * 1024));
+        assert_eq!("12.3G", super::fmt_bytes(13_207_024_435));
+        assert_eq!("16.0E", super::fmt_bytes(u64::MAX));
+    }
+
+    #[test]
+    fn t_fmt_duration() {
+        use std::time::Duration;
+
+        let secs = Duration::from_secs;
+        assert_eq!("0m", super::fmt_duration(secs(59)));
+        assert_eq!("2m", super::fmt_duration(secs(2 * 60 + 30)));
+        assert_eq!("1h 0m", super::fmt_duration(secs(3600)));
+        assert_eq!("3h 2m", super::fmt_duration(secs(3 * 3600 + 2 * 60)));
+        assert_eq!("4d 3h", super::fmt_duration(secs(356_521)));
+    }
+}
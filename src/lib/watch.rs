@@ -0,0 +1,96 @@
+// Filesystem change notifications, optionally merged with periodic polling,
+// since not everything (sysfs attributes in particular) reliably emits them.
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::clock::{self, Tick};
+
+type EventResult = Result<notify::Event, notify::Error>;
+
+/// Marks the events which came from polling, rather than from a change.
+const POLL: &str = "poll";
+
+pub struct Watcher {
+    _watcher: notify::RecommendedWatcher, // XXX To keep it from being dropped.
+    receiver: Receiver<EventResult>,
+    first: bool,
+    polled: bool,
+}
+
+impl Watcher {
+    /// First tick is immediate, subsequent ones after any of the given paths
+    /// change or, if given, after each poll interval.
+    pub fn new(
+        paths: &[PathBuf],
+        poll_interval: Option<Duration>,
+    ) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        if let Some(interval) = poll_interval {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for Tick in clock::new(interval).skip(1) {
+                    let poll = notify::Event::new(notify::EventKind::Other)
+                        .set_info(POLL);
+                    if sender.send(Ok(poll)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let mut _watcher = notify::recommended_watcher(sender)?;
+        for path in paths {
+            use notify::Watcher;
+            tracing::debug!(?path, "Watching.");
+            if let Err(error) =
+                _watcher.watch(path, notify::RecursiveMode::NonRecursive)
+            {
+                tracing::warn!(?path, ?error, "Failed to watch path.");
+            }
+        }
+        Ok(Self {
+            _watcher,
+            receiver,
+            first: true,
+            polled: false,
+        })
+    }
+
+    /// Whether the latest tick came from polling, rather than from any of
+    /// the paths being notified of a change.
+    pub fn polled(&self) -> bool {
+        self.polled
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first {
+            self.first = false;
+            return Some(Tick);
+        }
+        loop {
+            match self.receiver.recv().ok()? {
+                // XXX Our own reads would otherwise trigger more reads.
+                Ok(notify::Event {
+                    kind: notify::EventKind::Access(_),
+                    ..
+                }) => {}
+                Ok(event) => {
+                    tracing::trace!(?event, "Change.");
+                    self.polled = event.info() == Some(POLL);
+                    return Some(Tick);
+                }
+                Err(error) => {
+                    tracing::error!(?error, "Watch event error.");
+                }
+            }
+        }
+    }
+}